@@ -0,0 +1,127 @@
+use chrono::TimeDelta;
+use serde::Serialize;
+
+use crate::{
+    analytics::ser_seconds,
+    model::{AlertLevel, Thresholds, TimeSeries},
+};
+
+/// Time a series spent above one of the station thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TimeAbove {
+    level: AlertLevel,
+    threshold: f32,
+    #[serde(rename = "seconds", serialize_with = "ser_seconds")]
+    duration: TimeDelta,
+}
+
+impl TimeAbove {
+    /// Level reached when the threshold is exceeded.
+    pub fn level(&self) -> AlertLevel {
+        self.level
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    pub fn duration(&self) -> TimeDelta {
+        self.duration
+    }
+}
+
+/// Time spent above each defined threshold, from soglia1 to soglia3.
+///
+/// The series is assumed to change linearly between two consecutive readings, so crossings
+/// are placed where the line meets the threshold; time during outages is not counted.
+pub fn time_above(series: &TimeSeries, thresholds: &Thresholds) -> Vec<TimeAbove> {
+    let runs = series.runs();
+
+    thresholds
+        .levels()
+        .map(|(level, threshold)| {
+            let limit = f64::from(threshold);
+            let seconds = runs
+                .iter()
+                .flat_map(|run| run.windows(2))
+                .map(|pair| {
+                    let [(start, from), (end, to)] = pair else {
+                        return 0.0;
+                    };
+                    let elapsed = (*end - *start).num_milliseconds() as f64 / 1000.0;
+                    elapsed * share_above(*from, *to, limit)
+                })
+                .sum::<f64>();
+
+            TimeAbove {
+                level,
+                threshold,
+                duration: TimeDelta::seconds(seconds.round() as i64),
+            }
+        })
+        .collect()
+}
+
+/// Share of the segment from `from` to `to` that lies above `limit`.
+fn share_above(from: f64, to: f64, limit: f64) -> f64 {
+    match (from > limit, to > limit) {
+        (true, true) => 1.0,
+        (false, false) => 0.0,
+        (true, false) => (from - limit) / (from - to),
+        (false, true) => (to - limit) / (to - from),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::tests::series;
+
+    #[test]
+    fn crossings_are_interpolated_linearly() {
+        let series = series(&[(0, Some(0.0)), (60, Some(4.0)), (120, Some(0.0))]);
+        let thresholds = Thresholds::new(Some(1.0), None, Some(3.0));
+
+        let above = time_above(&series, &thresholds);
+
+        let durations = above
+            .iter()
+            .map(|above| {
+                (
+                    above.level(),
+                    above.threshold(),
+                    above.duration().num_minutes(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            durations,
+            [(AlertLevel::Level1, 1.0, 90), (AlertLevel::Level3, 3.0, 30)]
+        );
+        assert_eq!(serde_json::to_value(above[1]).unwrap()["seconds"], 1800);
+    }
+
+    #[test]
+    fn outages_are_not_counted() {
+        let series = series(&[
+            (0, Some(2.0)),
+            (60, None),
+            (120, Some(2.0)),
+            (180, Some(2.0)),
+        ]);
+        let thresholds = Thresholds::new(Some(1.0), None, None);
+
+        assert_eq!(
+            time_above(&series, &thresholds)[0].duration(),
+            TimeDelta::hours(1)
+        );
+    }
+
+    #[test]
+    fn share_above_covers_every_direction() {
+        assert_eq!(share_above(2.0, 3.0, 1.0), 1.0);
+        assert_eq!(share_above(0.0, 0.5, 1.0), 0.0);
+        assert_eq!(share_above(3.0, 0.0, 1.0), 2.0 / 3.0);
+        assert_eq!(share_above(0.0, 4.0, 1.0), 0.75);
+    }
+}
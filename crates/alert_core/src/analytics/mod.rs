@@ -0,0 +1,154 @@
+//! Numbers derived from a [`TimeSeries`], so that the TUI and alerting scripts agree on them.
+//!
+//! Nothing is interpolated across a missing reading: rates and peaks are computed within the
+//! runs of [`TimeSeries::runs`].
+
+mod exceedance;
+mod peaks;
+mod projection;
+mod rate;
+mod rolling;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Serialize, Serializer};
+
+pub use crate::analytics::{
+    exceedance::{TimeAbove, time_above},
+    peaks::{Peak, peaks},
+    projection::{
+        Crossing, PROJECTION_HORIZON, PROJECTION_WINDOW, Projection, ThresholdCrossing, Trend,
+        project, project_over,
+    },
+    rate::{Rate, rate_of_change, rate_over},
+    rolling::{RollingStats, rolling},
+};
+use crate::model::{Thresholds, TimeSeries};
+
+/// Window of [`Summary::rate_per_hour`].
+pub const RATE_WINDOW: TimeDelta = TimeDelta::hours(1);
+/// Window of [`Summary::stats`].
+pub const STATS_WINDOW: TimeDelta = TimeDelta::hours(24);
+/// Share of the series range a peak must stand out by to be listed in [`Summary::peaks`].
+pub const PEAK_PROMINENCE_RATIO: f64 = 0.1;
+
+/// The figures shown next to a station graph.
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    latest: Option<(DateTime<Utc>, f64)>,
+    rate_per_hour: Option<f64>,
+    stats: Option<RollingStats>,
+    peaks: Vec<Peak>,
+    time_above: Vec<TimeAbove>,
+}
+
+impl Summary {
+    pub fn new(series: &TimeSeries, thresholds: &Thresholds) -> Self {
+        let runs = series.runs();
+        let latest = runs.last().and_then(|run| run.last()).copied();
+
+        let (min, max) = runs.iter().flatten().fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |(min, max), (_, value)| (min.min(*value), max.max(*value)),
+        );
+        let min_prominence = if min <= max {
+            (max - min) * PEAK_PROMINENCE_RATIO
+        } else {
+            0.0
+        };
+
+        Self {
+            latest,
+            rate_per_hour: rate_over(series, RATE_WINDOW),
+            stats: rolling(&series.last(STATS_WINDOW), STATS_WINDOW).pop(),
+            peaks: peaks(series, min_prominence),
+            time_above: time_above(series, thresholds),
+        }
+    }
+
+    /// Most recent reading with a value.
+    pub fn latest(&self) -> Option<(DateTime<Utc>, f64)> {
+        self.latest
+    }
+
+    /// Rate of change over the last [`RATE_WINDOW`].
+    pub fn rate_per_hour(&self) -> Option<f64> {
+        self.rate_per_hour
+    }
+
+    /// Statistics over the last [`STATS_WINDOW`].
+    pub fn stats(&self) -> Option<&RollingStats> {
+        self.stats.as_ref()
+    }
+
+    /// Peaks standing out by at least [`PEAK_PROMINENCE_RATIO`] of the series range.
+    pub fn peaks(&self) -> &[Peak] {
+        &self.peaks
+    }
+
+    pub fn time_above(&self) -> &[TimeAbove] {
+        &self.time_above
+    }
+}
+
+fn hours(delta: TimeDelta) -> f64 {
+    delta.num_milliseconds() as f64 / 3_600_000.0
+}
+
+fn ser_seconds<S: Serializer>(delta: &TimeDelta, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i64(delta.num_seconds())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::model::{AlertLevel, TimeValue};
+
+    /// Series with a reading every `(minutes since the epoch, value)`.
+    pub(crate) fn series(readings: &[(i64, Option<f64>)]) -> TimeSeries {
+        TimeSeries::new(
+            readings
+                .iter()
+                .map(|&(minutes, value)| {
+                    TimeValue::new(DateTime::UNIX_EPOCH + TimeDelta::minutes(minutes), value)
+                        .unwrap()
+                })
+                .collect(),
+        )
+    }
+
+    pub(crate) fn at(minutes: i64) -> DateTime<Utc> {
+        DateTime::UNIX_EPOCH + TimeDelta::minutes(minutes)
+    }
+
+    #[test]
+    fn summary_of_a_flood_wave() {
+        let series = series(&[
+            (0, Some(1.0)),
+            (60, Some(2.0)),
+            (120, Some(4.0)),
+            (180, Some(3.0)),
+            (240, None),
+            (300, Some(2.0)),
+        ]);
+        let thresholds = Thresholds::new(Some(2.5), Some(5.0), None);
+
+        let summary = Summary::new(&series, &thresholds);
+
+        assert_eq!(summary.latest(), Some((at(300), 2.0)));
+        assert_eq!(summary.rate_per_hour(), None);
+        assert_eq!(summary.stats().map(RollingStats::max), Some(4.0));
+        assert_eq!(summary.peaks().len(), 1);
+        assert_eq!(summary.peaks()[0].time(), at(120));
+        let levels = summary.time_above().iter().map(TimeAbove::level);
+        assert!(levels.eq([AlertLevel::Level1, AlertLevel::Level2]));
+    }
+
+    #[test]
+    fn summary_of_an_empty_series() {
+        let summary = Summary::new(&TimeSeries::new(Vec::new()), &Thresholds::default());
+
+        assert_eq!(summary.latest(), None);
+        assert!(summary.stats().is_none());
+        assert!(summary.peaks().is_empty() && summary.time_above().is_empty());
+    }
+}
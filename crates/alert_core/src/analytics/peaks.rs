@@ -0,0 +1,132 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::model::TimeSeries;
+
+/// A local maximum of a time series.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Peak {
+    time: DateTime<Utc>,
+    value: f64,
+    prominence: f64,
+}
+
+impl Peak {
+    /// Time of the peak, the first reading when it lasts for several readings.
+    pub fn time(&self) -> DateTime<Utc> {
+        self.time
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// How much the peak stands out: its height above the higher of the lowest points that
+    /// separate it from a higher reading on either side.
+    pub fn prominence(&self) -> f64 {
+        self.prominence
+    }
+}
+
+/// Local maxima standing out by at least `min_prominence`, in chronological order.
+///
+/// The first and last reading of a run are never peaks, as it is unknown what came before or
+/// after them.
+pub fn peaks(series: &TimeSeries, min_prominence: f64) -> Vec<Peak> {
+    let mut peaks = Vec::new();
+
+    for run in series.runs() {
+        let values = run.iter().map(|(_, value)| *value).collect::<Vec<_>>();
+
+        let mut index = 1;
+        while index + 1 < values.len() {
+            let value = values[index];
+            // Readings equal to the candidate are a plateau, looked past as a whole.
+            let plateau_end = (index..values.len())
+                .take_while(|next| values[*next] == value)
+                .last()
+                .unwrap_or(index);
+
+            if values[index - 1] < value
+                && values
+                    .get(plateau_end + 1)
+                    .is_some_and(|next| *next < value)
+            {
+                let left_base = values[..index]
+                    .iter()
+                    .rev()
+                    .take_while(|previous| **previous <= value)
+                    .copied()
+                    .fold(value, f64::min);
+                let right_base = values[plateau_end + 1..]
+                    .iter()
+                    .take_while(|next| **next <= value)
+                    .copied()
+                    .fold(value, f64::min);
+                let prominence = value - left_base.max(right_base);
+
+                if prominence >= min_prominence {
+                    peaks.push(Peak {
+                        time: run[index].0,
+                        value,
+                        prominence,
+                    });
+                }
+            }
+
+            index = plateau_end + 1;
+        }
+    }
+
+    peaks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::tests::{at, series};
+
+    fn values(values: &[f64]) -> TimeSeries {
+        let readings = values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (index as i64 * 15, Some(*value)))
+            .collect::<Vec<_>>();
+        series(&readings)
+    }
+
+    #[test]
+    fn prominence_is_measured_from_the_higher_base() {
+        let peaks = peaks(&values(&[0.0, 3.0, 1.0, 5.0, 2.0, 4.0, 0.0]), 0.0);
+
+        let found = peaks
+            .iter()
+            .map(|peak| (peak.time(), peak.value(), peak.prominence()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [(at(15), 3.0, 2.0), (at(45), 5.0, 5.0), (at(75), 4.0, 2.0)]
+        );
+    }
+
+    #[test]
+    fn plateaus_are_a_single_peak_at_their_start() {
+        let peaks = peaks(&values(&[1.0, 2.0, 2.0, 2.0, 1.0]), 0.0);
+
+        assert_eq!(peaks.len(), 1);
+        assert_eq!(peaks[0].time(), at(15));
+    }
+
+    #[test]
+    fn edges_shoulders_and_small_bumps_are_not_peaks() {
+        assert!(peaks(&values(&[3.0, 2.0, 1.0]), 0.0).is_empty());
+        assert!(peaks(&values(&[1.0, 2.0, 2.0, 3.0]), 0.0).is_empty());
+        assert!(peaks(&values(&[1.0, 1.2, 1.0]), 0.5).is_empty());
+    }
+
+    #[test]
+    fn outages_cut_peaks() {
+        let series = series(&[(0, Some(1.0)), (15, Some(3.0)), (30, None), (45, Some(1.0))]);
+        assert!(peaks(&series, 0.0).is_empty());
+    }
+}
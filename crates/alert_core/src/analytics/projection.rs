@@ -0,0 +1,358 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+
+use crate::{
+    analytics::hours,
+    model::{AlertLevel, Station, TimeSeries},
+};
+
+/// Readings the trend is fitted on, leading up to the most recent one.
+pub const PROJECTION_WINDOW: TimeDelta = TimeDelta::hours(3);
+/// How far ahead crossings are estimated; a slower trend is reported as
+/// [`Crossing::BeyondHorizon`].
+pub const PROJECTION_HORIZON: TimeDelta = TimeDelta::hours(72);
+/// Fewest readings a trend is fitted on.
+const MIN_READINGS: usize = 4;
+
+/// Two-sided 95% quantiles of Student's t distribution by degrees of freedom, from 1 to 30.
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+/// The normal quantile, close enough past 30 degrees of freedom.
+const Z_95: f64 = 1.96;
+
+/// Least squares line through the recent readings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Trend {
+    /// Time of the most recent reading, where the line is evaluated.
+    time: DateTime<Utc>,
+    /// Value of the line at `time`.
+    value: f64,
+    per_hour: f64,
+    /// 95% confidence interval of `per_hour`.
+    per_hour_low: f64,
+    per_hour_high: f64,
+    readings: usize,
+}
+
+impl Trend {
+    pub fn time(&self) -> DateTime<Utc> {
+        self.time
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Fitted change in the variable unit per hour.
+    pub fn per_hour(&self) -> f64 {
+        self.per_hour
+    }
+
+    /// Bounds of the 95% confidence interval of [`Trend::per_hour`].
+    pub fn per_hour_interval(&self) -> (f64, f64) {
+        (self.per_hour_low, self.per_hour_high)
+    }
+
+    /// Number of readings the line was fitted on.
+    pub fn readings(&self) -> usize {
+        self.readings
+    }
+
+    fn fit(points: &[(DateTime<Utc>, f64)]) -> Option<Self> {
+        let &(time, _) = points.last()?;
+        if points.len() < MIN_READINGS {
+            return None;
+        }
+
+        let n = points.len() as f64;
+        let xs = points
+            .iter()
+            .map(|(at, value)| (hours(*at - time), *value))
+            .collect::<Vec<_>>();
+        let x_mean = xs.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let y_mean = xs.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let sxx = xs.iter().map(|(x, _)| (x - x_mean).powi(2)).sum::<f64>();
+        if sxx == 0.0 {
+            return None;
+        }
+        let sxy = xs
+            .iter()
+            .map(|(x, y)| (x - x_mean) * (y - y_mean))
+            .sum::<f64>();
+
+        let per_hour = sxy / sxx;
+        let value = y_mean - per_hour * x_mean;
+        let residuals = xs
+            .iter()
+            .map(|(x, y)| (y - value - per_hour * x).powi(2))
+            .sum::<f64>();
+        let degrees = points.len() - 2;
+        let standard_error = (residuals / degrees as f64 / sxx).sqrt();
+        let margin = T_95.get(degrees - 1).copied().unwrap_or(Z_95) * standard_error;
+
+        Some(Self {
+            time,
+            value,
+            per_hour,
+            per_hour_low: per_hour - margin,
+            per_hour_high: per_hour + margin,
+            readings: points.len(),
+        })
+    }
+
+    /// Time the line reaches `threshold` when rising at `per_hour`, if within the horizon.
+    fn reaches(&self, threshold: f64, per_hour: f64) -> Option<DateTime<Utc>> {
+        if per_hour <= 0.0 {
+            return None;
+        }
+        let ahead = ((threshold - self.value) / per_hour).max(0.0);
+        if ahead > hours(PROJECTION_HORIZON) {
+            return None;
+        }
+        Some(self.time + TimeDelta::seconds((ahead * 3600.0).round() as i64))
+    }
+}
+
+/// When a threshold is expected to be crossed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Crossing {
+    /// The most recent reading is already above the threshold.
+    Exceeded,
+    /// Crossing estimated from the trend, with the bounds given by the confidence interval of
+    /// its slope; `latest` is `None` when that interval doesn't rule out a flat trend.
+    Expected {
+        at: DateTime<Utc>,
+        earliest: DateTime<Utc>,
+        latest: Option<DateTime<Utc>>,
+    },
+    /// Rising, but not fast enough to cross within [`PROJECTION_HORIZON`].
+    BeyondHorizon,
+    NotRising,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ThresholdCrossing {
+    level: AlertLevel,
+    threshold: f32,
+    crossing: Crossing,
+}
+
+impl ThresholdCrossing {
+    /// Level reached when the threshold is exceeded.
+    pub fn level(&self) -> AlertLevel {
+        self.level
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    pub fn crossing(&self) -> Crossing {
+        self.crossing
+    }
+}
+
+/// Estimated crossings of the thresholds of a station, from its recent trend.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Projection {
+    trend: Option<Trend>,
+    crossings: Vec<ThresholdCrossing>,
+}
+
+impl Projection {
+    /// The fitted trend, `None` without enough recent readings.
+    pub fn trend(&self) -> Option<&Trend> {
+        self.trend.as_ref()
+    }
+
+    /// One entry per defined threshold, from soglia1 to soglia3, empty without a trend.
+    pub fn crossings(&self) -> &[ThresholdCrossing] {
+        &self.crossings
+    }
+
+    /// The lowest threshold not exceeded yet.
+    pub fn next(&self) -> Option<&ThresholdCrossing> {
+        self.crossings
+            .iter()
+            .find(|crossing| crossing.crossing != Crossing::Exceeded)
+    }
+
+    pub fn is_rising(&self) -> bool {
+        self.trend.is_some_and(|trend| trend.per_hour > 0.0)
+    }
+}
+
+/// Projects the thresholds of `station` on the trend of the last [`PROJECTION_WINDOW`] of
+/// `series`.
+pub fn project(station: &Station, series: &TimeSeries) -> Projection {
+    project_over(station, series, PROJECTION_WINDOW)
+}
+
+/// [`project`] fitting the trend over the last `window` instead.
+pub fn project_over(station: &Station, series: &TimeSeries, window: TimeDelta) -> Projection {
+    let points = series
+        .last(window)
+        .runs()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    let Some(trend) = Trend::fit(&points) else {
+        return Projection {
+            trend: None,
+            crossings: Vec::new(),
+        };
+    };
+    let latest = points.last().map_or(trend.value, |(_, value)| *value);
+
+    let crossings = station
+        .thresholds()
+        .levels()
+        .map(|(level, threshold)| {
+            let limit = f64::from(threshold);
+            let crossing = if latest > limit {
+                Crossing::Exceeded
+            } else if trend.per_hour <= 0.0 {
+                Crossing::NotRising
+            } else {
+                match (
+                    trend.reaches(limit, trend.per_hour),
+                    trend.reaches(limit, trend.per_hour_high),
+                ) {
+                    (Some(at), Some(earliest)) => Crossing::Expected {
+                        at,
+                        earliest,
+                        latest: trend.reaches(limit, trend.per_hour_low),
+                    },
+                    _ => Crossing::BeyondHorizon,
+                }
+            };
+            ThresholdCrossing {
+                level,
+                threshold,
+                crossing,
+            }
+        })
+        .collect();
+
+    Projection {
+        trend: Some(trend),
+        crossings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analytics::tests::{at, series},
+        model::{
+            Thresholds,
+            fixtures::{ID, info},
+        },
+    };
+
+    fn station(thresholds: Thresholds) -> Station {
+        Station::from_parts(info(ID, 9999, thresholds), Some(1.0))
+    }
+
+    /// Readings every 15 minutes for the last two hours, rising by `per_hour`.
+    fn rising(per_hour: f64) -> TimeSeries {
+        let readings = (0..=8)
+            .map(|step| (step * 15, Some(1.0 + per_hour * step as f64 / 4.0)))
+            .collect::<Vec<_>>();
+        series(&readings)
+    }
+
+    #[test]
+    fn a_straight_line_has_an_exact_trend() {
+        let trend = Trend::fit(&rising(0.5).runs()[0]).unwrap();
+
+        assert_eq!(trend.time(), at(120));
+        assert!((trend.value() - 2.0).abs() < 1e-9);
+        assert!((trend.per_hour() - 0.5).abs() < 1e-9);
+        let (low, high) = trend.per_hour_interval();
+        assert!((high - low).abs() < 1e-9);
+        assert_eq!(trend.readings(), 9);
+    }
+
+    #[test]
+    fn crossings_are_placed_on_the_trend() {
+        let thresholds = Thresholds::new(Some(1.5), Some(3.0), Some(100.0));
+
+        let projection = project(&station(thresholds), &rising(0.5));
+
+        let crossings = projection
+            .crossings()
+            .iter()
+            .map(ThresholdCrossing::crossing)
+            .collect::<Vec<_>>();
+        assert_eq!(crossings[0], Crossing::Exceeded);
+        assert!(matches!(
+            crossings[1],
+            Crossing::Expected { at: time, .. } if time == at(240)
+        ));
+        assert_eq!(crossings[2], Crossing::BeyondHorizon);
+        assert_eq!(
+            projection.next().map(ThresholdCrossing::level),
+            Some(AlertLevel::Level2)
+        );
+        assert!(projection.is_rising());
+    }
+
+    #[test]
+    fn noisy_trends_have_wider_bounds() {
+        let noisy = series(&[
+            (0, Some(1.0)),
+            (15, Some(1.3)),
+            (30, Some(1.1)),
+            (45, Some(1.6)),
+            (60, Some(1.4)),
+            (75, Some(1.9)),
+        ]);
+
+        let projection = project(&station(Thresholds::new(Some(3.0), None, None)), &noisy);
+
+        let Crossing::Expected {
+            at,
+            earliest,
+            latest,
+        } = projection.crossings()[0].crossing()
+        else {
+            panic!("expected a crossing");
+        };
+        assert!(earliest < at);
+        assert!(latest.is_none_or(|latest| latest > at));
+    }
+
+    #[test]
+    fn falling_or_short_series_are_not_projected() {
+        let thresholds = Thresholds::new(Some(5.0), None, None);
+
+        let falling = project(&station(thresholds), &rising(-0.5));
+        assert!(!falling.is_rising());
+        assert_eq!(falling.crossings()[0].crossing(), Crossing::NotRising);
+
+        let short = series(&[(0, Some(1.0)), (15, Some(2.0)), (30, Some(3.0))]);
+        let projection = project(&station(thresholds), &short);
+        assert!(projection.trend().is_none() && projection.crossings().is_empty());
+    }
+
+    #[test]
+    fn only_the_recent_window_is_fitted() {
+        let mut readings = vec![(0, Some(10.0))];
+        readings.extend((0..=8).map(|step| (600 + step * 15, Some(1.0 + step as f64 / 8.0))));
+
+        let projection = project_over(
+            &station(Thresholds::default()),
+            &series(&readings),
+            TimeDelta::hours(2),
+        );
+
+        assert_eq!(projection.trend().map(Trend::readings), Some(9));
+        assert!(projection.is_rising());
+    }
+}
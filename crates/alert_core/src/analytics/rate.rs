@@ -0,0 +1,93 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+
+use crate::{analytics::hours, model::TimeSeries};
+
+/// Rate of change between a reading and the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Rate {
+    time: DateTime<Utc>,
+    per_hour: f64,
+}
+
+impl Rate {
+    /// Time of the later of the two readings.
+    pub fn time(&self) -> DateTime<Utc> {
+        self.time
+    }
+
+    /// Change in the variable unit per hour, positive when rising.
+    pub fn per_hour(&self) -> f64 {
+        self.per_hour
+    }
+}
+
+/// Rate of change between each pair of consecutive readings.
+pub fn rate_of_change(series: &TimeSeries) -> Vec<Rate> {
+    series
+        .runs()
+        .iter()
+        .flat_map(|run| run.windows(2))
+        .filter_map(|pair| {
+            let [(start, from), (end, to)] = pair else {
+                return None;
+            };
+            per_hour(*end - *start, to - from).map(|per_hour| Rate {
+                time: *end,
+                per_hour,
+            })
+        })
+        .collect()
+}
+
+/// Average rate of change over the `window` leading up to the most recent reading.
+///
+/// `None` when fewer than two readings with a value fall in the window.
+pub fn rate_over(series: &TimeSeries, window: TimeDelta) -> Option<f64> {
+    let recent = series.last(window);
+    let mut readings = recent.runs().into_iter().flatten();
+    let (start, from) = readings.next()?;
+    let (end, to) = readings.last()?;
+    per_hour(end - start, to - from)
+}
+
+fn per_hour(elapsed: TimeDelta, change: f64) -> Option<f64> {
+    (elapsed > TimeDelta::zero()).then(|| change / hours(elapsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::tests::{at, series};
+
+    #[test]
+    fn rates_are_per_hour_and_skip_outages() {
+        let series = series(&[
+            (0, Some(1.0)),
+            (30, Some(2.0)),
+            (45, None),
+            (60, Some(5.0)),
+            (75, Some(4.5)),
+        ]);
+
+        let rates = rate_of_change(&series);
+
+        assert_eq!(rates.len(), 2);
+        assert_eq!((rates[0].time(), rates[0].per_hour()), (at(30), 2.0));
+        assert_eq!((rates[1].time(), rates[1].per_hour()), (at(75), -2.0));
+    }
+
+    #[test]
+    fn rate_over_a_window_uses_its_first_and_last_reading() {
+        let series = series(&[
+            (0, Some(0.0)),
+            (60, Some(1.0)),
+            (90, None),
+            (120, Some(3.0)),
+        ]);
+
+        assert_eq!(rate_over(&series, TimeDelta::hours(1)), Some(2.0));
+        assert_eq!(rate_over(&series, TimeDelta::hours(2)), Some(1.5));
+        assert_eq!(rate_over(&series, TimeDelta::minutes(30)), None);
+    }
+}
@@ -0,0 +1,106 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Serialize;
+
+use crate::model::TimeSeries;
+
+/// Statistics of the readings in a window of time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RollingStats {
+    end: DateTime<Utc>,
+    min: f64,
+    max: f64,
+    mean: f64,
+    count: usize,
+}
+
+impl RollingStats {
+    /// Time of the last reading in the window.
+    pub fn end(&self) -> DateTime<Utc> {
+        self.end
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Number of readings with a value in the window.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Statistics over the `window` ending at each reading with a value, that reading included.
+///
+/// Missing readings are skipped, so a window spanning an outage covers fewer readings.
+pub fn rolling(series: &TimeSeries, window: TimeDelta) -> Vec<RollingStats> {
+    let readings = series.runs().into_iter().flatten().collect::<Vec<_>>();
+
+    let mut start = 0;
+    let mut sum = 0.0;
+    readings
+        .iter()
+        .enumerate()
+        .map(|(index, (end, value))| {
+            sum += value;
+            while *end - readings[start].0 > window {
+                sum -= readings[start].1;
+                start += 1;
+            }
+
+            let in_window = &readings[start..=index];
+            let (min, max) = in_window.iter().fold(
+                (f64::INFINITY, f64::NEG_INFINITY),
+                |(min, max), (_, value)| (min.min(*value), max.max(*value)),
+            );
+            RollingStats {
+                end: *end,
+                min,
+                max,
+                mean: sum / in_window.len() as f64,
+                count: in_window.len(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::tests::{at, series};
+
+    #[test]
+    fn windows_drop_readings_that_are_too_old() {
+        let series = series(&[
+            (0, Some(1.0)),
+            (30, Some(3.0)),
+            (45, None),
+            (60, Some(5.0)),
+            (120, Some(2.0)),
+        ]);
+
+        let stats = rolling(&series, TimeDelta::hours(1));
+
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats[2].end(), at(60));
+        assert_eq!(
+            (stats[2].min(), stats[2].max(), stats[2].mean()),
+            (1.0, 5.0, 3.0)
+        );
+        assert_eq!(stats[2].count(), 3);
+        assert_eq!(stats[3].count(), 2);
+        assert_eq!(stats[3].mean(), 3.5);
+    }
+
+    #[test]
+    fn series_without_values_have_no_stats() {
+        assert!(rolling(&series(&[(0, None)]), TimeDelta::hours(1)).is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt::Debug;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// Source of the current time, so that "now" can be pinned or shifted, e.g. to replay a past
+/// event as if it was happening live.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system wall clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock stopped at a given instant.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock {
+    time: DateTime<Utc>,
+}
+
+impl FixedClock {
+    pub fn new(time: DateTime<Utc>) -> Self {
+        Self { time }
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.time
+    }
+}
+
+/// The system clock shifted by a fixed offset: time keeps flowing, starting from another point.
+#[derive(Clone, Copy, Debug)]
+pub struct OffsetClock {
+    offset: TimeDelta,
+}
+
+impl OffsetClock {
+    pub fn new(offset: TimeDelta) -> Self {
+        Self { offset }
+    }
+
+    /// A clock reading `time` right now and advancing from there.
+    pub fn starting_at(time: DateTime<Utc>) -> Self {
+        Self::new(time - Utc::now())
+    }
+
+    pub fn offset(&self) -> TimeDelta {
+        self.offset
+    }
+}
+
+impl Clock for OffsetClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now() + self.offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_clock_does_not_move() {
+        let time = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = FixedClock::new(time);
+        assert_eq!(clock.now(), time);
+        assert_eq!(clock.now(), time);
+    }
+
+    #[test]
+    fn offset_clock_starts_at_the_given_time_and_flows() {
+        let time = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = OffsetClock::starting_at(time);
+
+        let first = clock.now();
+        let second = clock.now();
+
+        assert!(first >= time && first - time < TimeDelta::seconds(5));
+        assert!(second >= first);
+        assert!(clock.offset() < TimeDelta::zero());
+    }
+}
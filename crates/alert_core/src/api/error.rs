@@ -1,20 +1,282 @@
+use std::path::PathBuf;
+
+use serde::ser::SerializeMap as _;
+
+const SNIPPET_MAX_CHARS: usize = 200;
+
 #[derive(thiserror::Error, Debug)]
 pub enum StationsError {
+    #[error("Upstream answered with HTTP {status}")]
+    Status {
+        status: reqwest::StatusCode,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("Request to upstream timed out")]
+    Timeout(#[source] reqwest::Error),
+    #[error("Couldn't connect to upstream")]
+    Connect(#[source] reqwest::Error),
+    #[error("Couldn't decode upstream response (body: {snippet:?})")]
+    Decode {
+        snippet: String,
+        #[source]
+        source: serde_json::Error,
+    },
     #[error("Couldn't get stations")]
-    Decode(#[from] reqwest::Error),
+    Request(#[source] reqwest::Error),
     #[error("Couldn't parse url")]
     Parse(#[from] url::ParseError),
-    #[error("Couldn't parse timeseries")]
-    Timeseries(#[from] serde_json::Error),
+    #[error("Couldn't read {path}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid {axis} coordinate: {value:?}")]
+    InvalidCoordinate { axis: &'static str, value: String },
+    #[error("Invalid station id: {0:?}")]
+    InvalidStationId(String),
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+    #[error("Invalid time range: {0}")]
+    InvalidRange(String),
+    #[error("Couldn't read local storage")]
+    Storage(#[from] crate::storage::StorageError),
+    #[error("Failed after {attempts} attempts")]
+    Retried {
+        attempts: u32,
+        #[source]
+        source: Box<StationsError>,
+    },
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
 
+impl From<reqwest::Error> for StationsError {
+    fn from(error: reqwest::Error) -> Self {
+        if let Some(status) = error.status() {
+            StationsError::Status {
+                status,
+                source: error,
+            }
+        } else if error.is_timeout() {
+            StationsError::Timeout(error)
+        } else if error.is_connect() {
+            StationsError::Connect(error)
+        } else {
+            StationsError::Request(error)
+        }
+    }
+}
+
+impl StationsError {
+    /// Builds a [`StationsError::Decode`] keeping the beginning of the offending body.
+    pub fn decode(body: &str, source: serde_json::Error) -> Self {
+        let mut snippet = body.chars().take(SNIPPET_MAX_CHARS).collect::<String>();
+        if snippet.len() < body.len() {
+            snippet.push('…');
+        }
+
+        StationsError::Decode { snippet, source }
+    }
+
+    /// Stable, machine readable name of the variant, also used as `kind` when serializing.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StationsError::Status { .. } => "status",
+            StationsError::Timeout(_) => "timeout",
+            StationsError::Connect(_) => "connect",
+            StationsError::Decode { .. } => "decode",
+            StationsError::Request(_) => "request",
+            StationsError::Parse(_) => "parse",
+            StationsError::Io { .. } => "io",
+            StationsError::InvalidCoordinate { .. } => "invalid_coordinate",
+            StationsError::InvalidStationId(_) => "invalid_station_id",
+            StationsError::InvalidTimestamp(_) => "invalid_timestamp",
+            StationsError::InvalidRange(_) => "invalid_range",
+            StationsError::Storage(_) => "storage",
+            StationsError::Retried { .. } => "retried",
+            StationsError::Unknown(_) => "unknown",
+        }
+    }
+
+    /// HTTP status returned by upstream, looking through retries.
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            StationsError::Status { status, .. } => Some(*status),
+            StationsError::Retried { source, .. } => source.status(),
+            _ => None,
+        }
+    }
+
+    /// Whether the failure is likely to go away on its own: timeouts, connection failures,
+    /// `429 Too Many Requests` and server errors.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            StationsError::Status { status, .. } => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            StationsError::Timeout(_) | StationsError::Connect(_) => true,
+            StationsError::Retried { source, .. } => source.is_transient(),
+            _ => false,
+        }
+    }
+
+    /// Number of attempts made before giving up.
+    pub fn attempts(&self) -> u32 {
+        match self {
+            StationsError::Retried { attempts, .. } => *attempts,
+            _ => 1,
+        }
+    }
+}
+
+/// Serializes as `{"kind": ..., "message": ..., "causes": [...]}` plus the fields specific to
+/// the variant (`status`, `snippet`, `attempts`/`source`).
 impl serde::Serialize for StationsError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(self.to_string().as_ref())
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+
+        match self {
+            StationsError::Status { status, .. } => {
+                map.serialize_entry("status", &status.as_u16())?;
+            }
+            StationsError::Decode { snippet, .. } => {
+                map.serialize_entry("snippet", snippet)?;
+            }
+            StationsError::Retried { attempts, source } => {
+                map.serialize_entry("attempts", attempts)?;
+                map.serialize_entry("source", source)?;
+            }
+            _ => {}
+        }
+
+        if !matches!(self, StationsError::Retried { .. }) {
+            let mut causes = Vec::new();
+            let mut cause = std::error::Error::source(self);
+            while let Some(error) = cause {
+                causes.push(error.to_string());
+                cause = error.source();
+            }
+            map.serialize_entry("causes", &causes)?;
+        }
+
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read as _, Write as _},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// Answers a single request on a local port with `status` and returns the client error.
+    async fn status_error(status: u16) -> StationsError {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 {status} Oops\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+            )
+            .unwrap();
+        });
+
+        reqwest::get(format!("http://{address}/"))
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .unwrap_err()
+            .into()
+    }
+
+    fn decode_error(body: &str) -> StationsError {
+        let source = serde_json::from_str::<serde_json::Value>(body).unwrap_err();
+        StationsError::decode(body, source)
+    }
+
+    #[tokio::test]
+    async fn classifies_statuses() {
+        let unavailable = status_error(503).await;
+        assert_eq!(unavailable.kind(), "status");
+        assert_eq!(unavailable.status().map(|s| s.as_u16()), Some(503));
+        assert!(unavailable.is_transient());
+
+        assert!(status_error(429).await.is_transient());
+        assert!(!status_error(404).await.is_transient());
+    }
+
+    #[tokio::test]
+    async fn classifies_connection_failures() {
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let error: StationsError = reqwest::get(format!("http://{address}/"))
+            .await
+            .unwrap_err()
+            .into();
+
+        assert_eq!(error.kind(), "connect");
+        assert!(error.is_transient());
+    }
+
+    #[test]
+    fn decode_keeps_the_beginning_of_the_body() {
+        let body = format!("<html>{}</html>", "x".repeat(500));
+
+        let StationsError::Decode { snippet, .. } = decode_error(&body) else {
+            panic!("expected a decode error");
+        };
+
+        assert_eq!(snippet.chars().count(), SNIPPET_MAX_CHARS + 1);
+        assert!(snippet.starts_with("<html>") && snippet.ends_with('…'));
+        assert_eq!(
+            decode_error("nope").to_string(),
+            "Couldn't decode upstream response (body: \"nope\")"
+        );
+    }
+
+    #[test]
+    fn retried_looks_through_to_the_source() {
+        let error = StationsError::Retried {
+            attempts: 3,
+            source: Box::new(decode_error("nope")),
+        };
+
+        assert_eq!(error.kind(), "retried");
+        assert_eq!(error.to_string(), "Failed after 3 attempts");
+        assert_eq!(error.attempts(), 3);
+        assert_eq!(error.status(), None);
+        assert!(!error.is_transient());
+        assert_eq!(decode_error("nope").attempts(), 1);
+    }
+
+    #[test]
+    fn serializes_kind_message_and_details() {
+        let json = serde_json::to_value(decode_error("nope")).unwrap();
+        assert_eq!(json["kind"], "decode");
+        assert_eq!(json["snippet"], "nope");
+        assert_eq!(json["causes"].as_array().unwrap().len(), 1);
+
+        let retried = StationsError::Retried {
+            attempts: 2,
+            source: Box::new(StationsError::InvalidStationId("x".into())),
+        };
+        let json = serde_json::to_value(retried).unwrap();
+        assert_eq!(json["attempts"], 2);
+        assert_eq!(json["source"]["kind"], "invalid_station_id");
+        assert!(json.get("causes").is_none());
     }
 }
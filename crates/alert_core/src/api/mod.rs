@@ -1,18 +1,61 @@
+mod clock;
 mod error;
+mod rate_limit;
+mod retry;
+mod time_zone;
+mod variable;
 
-use chrono::{DateTime, DurationRound as _, Local, TimeDelta, TimeZone};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-pub use crate::api::error::StationsError;
-use crate::model::{Station, Stations, TimeSeries, TimeValue};
+use chrono::{DateTime, DurationRound as _, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use futures_util::{Stream, StreamExt as _};
+use serde::de::DeserializeOwned;
 
-const STATIONS_URL: &str = "https://allertameteo.regione.emilia-romagna.it/o/api/allerta/get-sensor-values?variabile=254,0,0/1,-,-,-/B13215";
-const TIMESERIES_URL: &str =
-    "https://allertameteo.regione.emilia-romagna.it/o/api/allerta/get-time-series/";
+pub use crate::api::{
+    clock::{Clock, FixedClock, OffsetClock, SystemClock},
+    error::StationsError,
+    rate_limit::RateLimiter,
+    retry::RetryPolicy,
+    time_zone::{STATION_TIME_ZONE, parse_local_time, resolve_local_time},
+    variable::Variable,
+};
+use crate::model::{Station, StationId, Stations, StationsResponse, TimeSeries, TimeValue};
+
+pub const DEFAULT_BASE_URL: &str = "https://allertameteo.regione.emilia-romagna.it/o/api/allerta/";
+const STATIONS_PATH: &str = "get-sensor-values";
+const TIMESERIES_PATH: &str = "get-time-series/";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 pub const DELTA_15MIN: TimeDelta = TimeDelta::minutes(15);
+const DEFAULT_RANGE_CONCURRENCY: usize = 4;
 
 #[derive(Clone, Debug)]
 pub struct AlertClient {
     client: reqwest::Client,
+    stations_url: reqwest::Url,
+    timeseries_url: reqwest::Url,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    range_concurrency: usize,
+    clock: Arc<dyn Clock>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AlertClientBuilder {
+    base_url: String,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    user_agent: String,
+    proxy: Option<String>,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    range_concurrency: usize,
+    clock: Arc<dyn Clock>,
 }
 
 impl Default for AlertClient {
@@ -23,8 +66,28 @@ impl Default for AlertClient {
 
 impl AlertClient {
     pub fn new() -> Self {
-        Self {
-            client: reqwest::Client::new(),
+        Self::builder()
+            .build()
+            .expect("default client configuration should be valid")
+    }
+
+    pub fn builder() -> AlertClientBuilder {
+        AlertClientBuilder::default()
+    }
+
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_ref()
+    }
+
+    /// Waits for the rate limiter, if any, and returns how long the call was held back.
+    pub async fn throttle(&self) -> Duration {
+        match &self.rate_limiter {
+            Some(rate_limiter) => rate_limiter.acquire().await,
+            None => Duration::ZERO,
         }
     }
 
@@ -32,50 +95,330 @@ impl AlertClient {
     where
         T: TimeZone,
     {
-        let mut call = reqwest::Url::parse(STATIONS_URL)?;
-        call.query_pairs_mut()
-            .encoding_override(Some(&|s| s.as_bytes().into()))
-            .append_pair("time", &time.timestamp_millis().to_string());
-
-        let mut stations: Vec<Station> = self.client.get(call).send().await?.json().await?;
-        stations.sort_by(|a, b| b.cmp(a));
-        Ok(Stations::new(stations))
-    }
-
-    pub async fn station_timeseries(&self, station_id: &str) -> Result<TimeSeries, StationsError> {
-        let series = self
-            .client
-            .get(TIMESERIES_URL)
-            .query(&[
-                ("stazione", station_id),
-                ("variabile", "254,0,0/1,-,-,-/B13215"),
-            ])
-            .send()
-            .await?
-            .json::<Vec<TimeValue>>()
+        self.variable_stations_at(&Variable::HYDROMETRIC_LEVEL, time)
+            .await
+    }
+
+    pub async fn variable_stations_at<T>(
+        &self,
+        variable: &Variable,
+        time: DateTime<T>,
+    ) -> Result<Stations, StationsError>
+    where
+        T: TimeZone,
+    {
+        self.stations_response_at(variable, time)
+            .await
+            .map(StationsResponse::into_stations)
+    }
+
+    /// Like [`AlertClient::variable_stations_at`], also reporting the upstream records that
+    /// couldn't be decoded.
+    pub async fn stations_response_at<T>(
+        &self,
+        variable: &Variable,
+        time: DateTime<T>,
+    ) -> Result<StationsResponse, StationsError>
+    where
+        T: TimeZone,
+    {
+        // The variable code is sent verbatim, as the upstream web app does.
+        let mut call = self.stations_url.clone();
+        call.set_query(Some(&format!(
+            "variabile={}&time={}",
+            variable.code(),
+            time.timestamp_millis()
+        )));
+
+        let (mut response, waited): (StationsResponse, _) =
+            self.fetch_json(|| self.client.get(call.clone())).await?;
+        response.stations_mut().sort_by_alert_desc();
+        Ok(response.with_variable(variable.clone()).with_waited(waited))
+    }
+
+    /// Fetches every snapshot from `start` to `end` included, `step` apart.
+    ///
+    /// Both ends are aligned with [`clamp_station_time`] and `step` must be a positive multiple
+    /// of [`DELTA_15MIN`]. Slots are yielded in chronological order, each with its own result,
+    /// while up to the configured range concurrency requests run at the same time.
+    pub fn stations_between<T>(
+        &self,
+        start: DateTime<T>,
+        end: DateTime<T>,
+        step: TimeDelta,
+    ) -> Result<
+        impl Stream<Item = (DateTime<Utc>, Result<Stations, StationsError>)> + Send + use<T>,
+        StationsError,
+    >
+    where
+        T: TimeZone,
+    {
+        self.variable_stations_between(&Variable::HYDROMETRIC_LEVEL, start, end, step)
+    }
+
+    pub fn variable_stations_between<T>(
+        &self,
+        variable: &Variable,
+        start: DateTime<T>,
+        end: DateTime<T>,
+        step: TimeDelta,
+    ) -> Result<
+        impl Stream<Item = (DateTime<Utc>, Result<Stations, StationsError>)> + Send + use<T>,
+        StationsError,
+    >
+    where
+        T: TimeZone,
+    {
+        let slots = station_slots(start, end, step)?;
+        let client = self.clone();
+        let variable = variable.clone();
+
+        Ok(futures_util::stream::iter(slots)
+            .map(move |slot| {
+                let client = client.clone();
+                let variable = variable.clone();
+                async move { (slot, client.variable_stations_at(&variable, slot).await) }
+            })
+            .buffered(self.range_concurrency))
+    }
+
+    pub async fn station_timeseries(
+        &self,
+        station_id: &StationId,
+    ) -> Result<TimeSeries, StationsError> {
+        self.variable_timeseries(&Variable::HYDROMETRIC_LEVEL, station_id)
+            .await
+    }
+
+    pub async fn variable_timeseries(
+        &self,
+        variable: &Variable,
+        station_id: &StationId,
+    ) -> Result<TimeSeries, StationsError> {
+        let (series, _): (Vec<TimeValue>, _) = self
+            .fetch_json(|| {
+                self.client.get(self.timeseries_url.clone()).query(&[
+                    ("stazione", station_id.as_str()),
+                    ("variabile", variable.code()),
+                ])
+            })
             .await?;
 
-        Ok(TimeSeries::new(series))
+        Ok(TimeSeries::new(series).with_variable(variable.clone()))
+    }
+
+    /// Fetches the time series of every station in `ids`, with at most `concurrency` requests
+    /// in flight.
+    ///
+    /// Results are yielded as soon as they complete, so not in the order of `ids`; a failure
+    /// only affects its own station.
+    pub fn timeseries_for<I>(
+        &self,
+        ids: I,
+        concurrency: usize,
+    ) -> impl Stream<Item = (StationId, Result<TimeSeries, StationsError>)> + Send + use<I>
+    where
+        I: IntoIterator<Item = StationId>,
+        I::IntoIter: Send,
+    {
+        self.variable_timeseries_for(&Variable::HYDROMETRIC_LEVEL, ids, concurrency)
+    }
+
+    pub fn variable_timeseries_for<I>(
+        &self,
+        variable: &Variable,
+        ids: I,
+        concurrency: usize,
+    ) -> impl Stream<Item = (StationId, Result<TimeSeries, StationsError>)> + Send + use<I>
+    where
+        I: IntoIterator<Item = StationId>,
+        I::IntoIter: Send,
+    {
+        let client = self.clone();
+        let variable = variable.clone();
+
+        futures_util::stream::iter(ids)
+            .map(move |station_id| {
+                let client = client.clone();
+                let variable = variable.clone();
+                async move {
+                    let result = client.variable_timeseries(&variable, &station_id).await;
+                    (station_id, result)
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+    }
+
+    /// Sends the request built by `request` under the retry policy and the rate limiter, then
+    /// decodes the JSON body.
+    ///
+    /// Also returns how long this call waited for the rate limiter, summed over its attempts.
+    async fn fetch_json<T, F>(&self, request: F) -> Result<(T, Duration), StationsError>
+    where
+        T: DeserializeOwned,
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let waited = Mutex::new(Duration::ZERO);
+        let value = self
+            .retry_policy
+            .run(|| async {
+                let wait = self.throttle().await;
+                *waited.lock().unwrap() += wait;
+                let body = request().send().await?.error_for_status()?.text().await?;
+                serde_json::from_str(&body).map_err(|error| StationsError::decode(&body, error))
+            })
+            .await?;
+        Ok((value, waited.into_inner().unwrap()))
     }
 
     pub async fn latest_stations(&self) -> Result<Stations, StationsError> {
-        let now = latest_station_time()?;
+        let now = latest_station_time(self.clock.as_ref())?;
         self.stations_at(now).await
     }
 }
 
-pub fn latest_station_time() -> Result<DateTime<Local>, StationsError> {
-    let adjusted = Local::now();
+impl Default for AlertClientBuilder {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_owned(),
+            timeout: Some(DEFAULT_TIMEOUT),
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            user_agent: DEFAULT_USER_AGENT.to_owned(),
+            proxy: None,
+            retry_policy: RetryPolicy::default(),
+            rate_limiter: Some(RateLimiter::default()),
+            range_concurrency: DEFAULT_RANGE_CONCURRENCY,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl AlertClientBuilder {
+    /// Base URL of the `allerta` API, e.g. a local mock server.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Total time allowed for a single request, `None` waits forever.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Option<Duration>) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Proxy URL used for every request, e.g. `http://proxy.lab:3128`.
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limiter shared by the built client and all its clones, `None` disables throttling.
+    pub fn rate_limiter(mut self, rate_limiter: Option<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Number of slots [`AlertClient::stations_between`] keeps in flight.
+    pub fn range_concurrency(mut self, range_concurrency: usize) -> Self {
+        self.range_concurrency = range_concurrency.max(1);
+        self
+    }
+
+    /// Clock used to find the latest slot in [`AlertClient::latest_stations`].
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn build(self) -> Result<AlertClient, StationsError> {
+        let mut base_url = reqwest::Url::parse(&self.base_url)?;
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+
+        let mut client = reqwest::Client::builder().user_agent(self.user_agent);
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            client = client.connect_timeout(connect_timeout);
+        }
+        if let Some(proxy) = self.proxy {
+            client = client.proxy(reqwest::Proxy::all(proxy)?);
+        }
+
+        Ok(AlertClient {
+            client: client.build()?,
+            stations_url: base_url.join(STATIONS_PATH)?,
+            timeseries_url: base_url.join(TIMESERIES_PATH)?,
+            retry_policy: self.retry_policy,
+            rate_limiter: self.rate_limiter,
+            range_concurrency: self.range_concurrency,
+            clock: self.clock,
+        })
+    }
+}
+
+/// Most recent slot at the time given by `clock`, in [`STATION_TIME_ZONE`].
+pub fn latest_station_time(clock: &dyn Clock) -> Result<DateTime<Tz>, StationsError> {
+    let adjusted = clock.now().with_timezone(&STATION_TIME_ZONE);
     adjusted
         .duration_trunc(DELTA_15MIN)
         .map_err(|err| StationsError::Unknown(err.to_string()))
 }
 
-pub fn clamp_station_time(date: DateTime<Local>) -> Result<DateTime<Local>, StationsError> {
+pub fn clamp_station_time<T>(date: DateTime<T>) -> Result<DateTime<T>, StationsError>
+where
+    T: TimeZone,
+{
     date.duration_trunc(DELTA_15MIN)
         .map_err(|err| StationsError::Unknown(err.to_string()))
 }
 
+/// Slots from `start` to `end` included, both aligned with [`clamp_station_time`].
+pub fn station_slots<T>(
+    start: DateTime<T>,
+    end: DateTime<T>,
+    step: TimeDelta,
+) -> Result<impl Iterator<Item = DateTime<Utc>> + Send + use<T>, StationsError>
+where
+    T: TimeZone,
+{
+    if step <= TimeDelta::zero() || step.num_seconds() % DELTA_15MIN.num_seconds() != 0 {
+        return Err(StationsError::InvalidRange(format!(
+            "step must be a positive multiple of 15 minutes, got {step}"
+        )));
+    }
+
+    let start = clamp_station_time(start.with_timezone(&Utc))?;
+    let end = clamp_station_time(end.with_timezone(&Utc))?;
+    if end < start {
+        return Err(StationsError::InvalidRange(format!(
+            "end {end} is before start {start}"
+        )));
+    }
+
+    Ok(
+        std::iter::successors(Some(start), move |slot| slot.checked_add_signed(step))
+            .take_while(move |slot| *slot <= end),
+    )
+}
+
 pub async fn get_stations<T>(time: DateTime<T>) -> Result<Stations, StationsError>
 where
     T: TimeZone,
@@ -92,3 +435,263 @@ pub async fn get_station_timeseries(station: &Station) -> Result<TimeSeries, Sta
 pub async fn get_stations_now() -> Result<Stations, StationsError> {
     AlertClient::new().latest_stations().await
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead as _, BufReader, Write as _},
+        net::TcpListener,
+        sync::Mutex,
+    };
+
+    use super::*;
+
+    /// Local HTTP server answering every request with `respond(request head)`, one connection
+    /// per request. Returns its base URL and the heads received so far.
+    fn serve(
+        respond: impl Fn(&str) -> (u16, String) + Send + 'static,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/api", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut head = String::new();
+                let mut reader = BufReader::new(&stream);
+                while reader.read_line(&mut head).unwrap() > 2 && !head.ends_with("\r\n\r\n") {}
+                let (status, body) = respond(&head);
+                received.lock().unwrap().push(head);
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} Status\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        (base_url, requests)
+    }
+
+    fn client(base_url: &str) -> AlertClient {
+        AlertClient::builder()
+            .base_url(base_url)
+            .user_agent("tests")
+            .rate_limiter(None)
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn responses_report_their_own_rate_limiter_wait() {
+        let (base_url, _) = serve(|_| (200, "[]".to_owned()));
+        let rate_limiter = RateLimiter::new(1, 20.0);
+        let client = AlertClient::builder()
+            .base_url(base_url)
+            .rate_limiter(Some(rate_limiter.clone()))
+            .build()
+            .unwrap();
+        let variable = Variable::HYDROMETRIC_LEVEL;
+
+        let first = client.stations_response_at(&variable, Utc::now()).await;
+        let second = client.stations_response_at(&variable, Utc::now()).await;
+
+        assert_eq!(first.unwrap().waited(), Duration::ZERO);
+        let waited = second.unwrap().waited();
+        assert!(waited > Duration::ZERO);
+        assert_eq!(waited, rate_limiter.total_wait());
+    }
+
+    #[tokio::test]
+    async fn requests_are_sent_below_the_base_url() {
+        let (base_url, requests) = serve(|_| (200, "[]".to_owned()));
+        let time = DateTime::from_timestamp_millis(1_700_000_100_000).unwrap();
+
+        let stations = client(&base_url).stations_at(time).await.unwrap();
+
+        assert!(stations.is_empty());
+        let head = requests.lock().unwrap()[0].clone();
+        assert!(head.starts_with(
+            "GET /api/get-sensor-values?variabile=254,0,0/1,-,-,-/B13215&time=1700000100000 "
+        ));
+        assert!(head.to_lowercase().contains("user-agent: tests\r\n"));
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried() {
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        let (base_url, _) = serve(move |_| {
+            let mut calls = counter.lock().unwrap();
+            *calls += 1;
+            match *calls {
+                1 => (503, String::new()),
+                _ => (200, "[]".to_owned()),
+            }
+        });
+        let policy = RetryPolicy::default().initial_backoff(Duration::ZERO);
+        let client = AlertClient::builder()
+            .base_url(base_url)
+            .retry_policy(policy)
+            .build()
+            .unwrap();
+
+        assert!(client.stations_at(Utc::now()).await.is_ok());
+        assert_eq!(*calls.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let (base_url, requests) = serve(|_| (404, String::new()));
+        let client = AlertClient::builder().base_url(base_url).build().unwrap();
+
+        let error = client.stations_at(Utc::now()).await.err().unwrap();
+
+        assert_eq!(error.status(), Some(reqwest::StatusCode::NOT_FOUND));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected_when_building() {
+        let invalid_url = AlertClient::builder().base_url("not a url").build();
+        assert!(matches!(invalid_url, Err(StationsError::Parse(_))));
+
+        let invalid_proxy = AlertClient::builder().proxy("::").build();
+        assert!(matches!(invalid_proxy, Err(StationsError::Request(_))));
+    }
+
+    fn utc(rfc3339: &str) -> DateTime<Utc> {
+        rfc3339.parse().unwrap()
+    }
+
+    #[test]
+    fn slots_are_aligned_and_include_both_ends() {
+        let slots = station_slots(
+            utc("2024-05-01T10:07:00Z"),
+            utc("2024-05-01T11:00:00Z"),
+            TimeDelta::minutes(30),
+        )
+        .unwrap()
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            slots,
+            [
+                utc("2024-05-01T10:00:00Z"),
+                utc("2024-05-01T10:30:00Z"),
+                utc("2024-05-01T11:00:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn slots_reject_invalid_ranges() {
+        let start = utc("2024-05-01T10:00:00Z");
+        for step in [
+            TimeDelta::zero(),
+            TimeDelta::minutes(-15),
+            TimeDelta::minutes(20),
+        ] {
+            assert!(matches!(
+                station_slots(start, start, step),
+                Err(StationsError::InvalidRange(_))
+            ));
+        }
+        assert!(matches!(
+            station_slots(start, start - DELTA_15MIN, DELTA_15MIN),
+            Err(StationsError::InvalidRange(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn range_yields_every_slot_in_order() {
+        let (base_url, _) = serve(|head| {
+            if head.contains("time=1714557600000") {
+                (500, String::new())
+            } else {
+                (200, "[]".to_owned())
+            }
+        });
+        let client = AlertClient::builder()
+            .base_url(base_url)
+            .rate_limiter(None)
+            .retry_policy(RetryPolicy::none())
+            .range_concurrency(3)
+            .build()
+            .unwrap();
+
+        let results = client
+            .stations_between(
+                utc("2024-05-01T09:00:00Z"),
+                utc("2024-05-01T10:00:00Z"),
+                DELTA_15MIN,
+            )
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+
+        let slots = results.iter().map(|(slot, _)| *slot).collect::<Vec<_>>();
+        assert_eq!(slots.len(), 5);
+        assert!(slots.is_sorted());
+        let failed = results
+            .iter()
+            .filter(|(_, result)| result.is_err())
+            .map(|(slot, _)| *slot)
+            .collect::<Vec<_>>();
+        assert_eq!(failed, [utc("2024-05-01T10:00:00Z")]);
+    }
+
+    #[tokio::test]
+    async fn bulk_time_series_fail_per_station() {
+        let (base_url, requests) = serve(|head| {
+            if head.contains("stazione=-%2F2") {
+                (500, String::new())
+            } else {
+                (200, r#"[{"t": 1714557600000, "v": 1.5}]"#.to_owned())
+            }
+        });
+        let ids = ["-/1,1/simnbo", "-/2,2/simnbo", "-/3,3/simnbo"]
+            .map(|id| id.parse::<StationId>().unwrap());
+
+        let mut results = client(&base_url)
+            .timeseries_for(ids.clone(), 2)
+            .collect::<Vec<_>>()
+            .await;
+        results.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        assert_eq!(results.len(), 3);
+        for ((id, result), expected) in results.iter().zip(&ids) {
+            assert_eq!(id, expected);
+            assert_eq!(result.is_err(), id.as_str() == "-/2,2/simnbo");
+        }
+        assert_eq!(results[0].1.as_ref().map(TimeSeries::len).ok(), Some(1));
+    }
+
+    #[test]
+    fn latest_slot_is_truncated_in_the_station_zone() {
+        let clock = FixedClock::new(utc("2024-03-31T01:14:59Z"));
+
+        let latest = latest_station_time(&clock).unwrap();
+
+        assert_eq!(latest.timezone(), STATION_TIME_ZONE);
+        assert_eq!(latest.to_utc(), utc("2024-03-31T01:00:00Z"));
+        assert_eq!(latest.to_rfc3339(), "2024-03-31T03:00:00+02:00");
+    }
+
+    #[tokio::test]
+    async fn latest_stations_asks_for_the_clock_slot() {
+        let (base_url, requests) = serve(|_| (200, "[]".to_owned()));
+        let client = AlertClient::builder()
+            .base_url(base_url)
+            .clock(Arc::new(FixedClock::new(utc("2024-05-01T10:07:00Z"))))
+            .build()
+            .unwrap();
+
+        client.latest_stations().await.unwrap();
+
+        assert!(requests.lock().unwrap()[0].contains("&time=1714557600000 "));
+    }
+}
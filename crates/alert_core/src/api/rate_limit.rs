@@ -0,0 +1,147 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const DEFAULT_BURST: u32 = 5;
+const DEFAULT_REQUESTS_PER_SECOND: f64 = 2.0;
+
+/// Token bucket shared by every clone, so that all the tasks using the same
+/// [`AlertClient`](crate::api::AlertClient) draw from a single budget.
+///
+/// Permits are reserved in arrival order: a caller that finds the bucket empty books the next
+/// token and sleeps until it is due, instead of polling.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    burst: f64,
+    requests_per_second: f64,
+    tokens: f64,
+    updated_at: Instant,
+    total_wait: Duration,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_BURST, DEFAULT_REQUESTS_PER_SECOND)
+    }
+}
+
+impl RateLimiter {
+    /// Allows `burst` requests at once, refilled at `requests_per_second`.
+    pub fn new(burst: u32, requests_per_second: f64) -> Self {
+        let burst = f64::from(burst.max(1));
+        let requests_per_second = if requests_per_second.is_finite() && requests_per_second > 0.0 {
+            requests_per_second
+        } else {
+            DEFAULT_REQUESTS_PER_SECOND
+        };
+
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                burst,
+                requests_per_second,
+                tokens: burst,
+                updated_at: Instant::now(),
+                total_wait: Duration::ZERO,
+            })),
+        }
+    }
+
+    /// Waits for a permit and returns how long the caller was held back.
+    pub async fn acquire(&self) -> Duration {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        wait
+    }
+
+    /// Time spent waiting for permits by all the users of this limiter, every clone included.
+    ///
+    /// The wait of a single snapshot request is reported by
+    /// [`StationsResponse::waited`](crate::model::StationsResponse::waited).
+    pub fn total_wait(&self) -> Duration {
+        self.lock().total_wait
+    }
+
+    fn reserve(&self) -> Duration {
+        let mut bucket = self.lock();
+        let now = Instant::now();
+        let elapsed = now
+            .saturating_duration_since(bucket.updated_at)
+            .as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * bucket.requests_per_second).min(bucket.burst);
+        bucket.updated_at = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+
+        let wait = Duration::from_secs_f64(-bucket.tokens / bucket.requests_per_second);
+        bucket.total_wait += wait;
+        wait
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Bucket> {
+        self.bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_is_free_then_waits_are_booked_in_order() {
+        let limiter = RateLimiter::new(3, 2.0);
+
+        for _ in 0..3 {
+            assert_eq!(limiter.reserve(), Duration::ZERO);
+        }
+        let first = limiter.reserve();
+        let second = limiter.reserve();
+
+        assert!(first > Duration::from_millis(400) && first <= Duration::from_millis(500));
+        assert!(second > Duration::from_millis(900) && second <= Duration::from_secs(1));
+        assert_eq!(limiter.total_wait(), first + second);
+    }
+
+    #[test]
+    fn clones_share_the_bucket() {
+        let limiter = RateLimiter::new(1, 1.0);
+        let clone = limiter.clone();
+
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+        assert!(!clone.reserve().is_zero());
+        assert_eq!(limiter.total_wait(), clone.total_wait());
+    }
+
+    #[test]
+    fn invalid_settings_fall_back_to_defaults() {
+        let limiter = RateLimiter::new(0, f64::NAN);
+        let bucket = limiter.lock();
+
+        assert_eq!(bucket.burst, 1.0);
+        assert_eq!(bucket.requests_per_second, DEFAULT_REQUESTS_PER_SECOND);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn acquire_sleeps_for_the_reserved_wait() {
+        let limiter = RateLimiter::new(1, 10.0);
+        limiter.acquire().await;
+
+        let start = tokio::time::Instant::now();
+        let wait = limiter.acquire().await;
+
+        assert!(!wait.is_zero());
+        assert!(start.elapsed() >= wait);
+    }
+}
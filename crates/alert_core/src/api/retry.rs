@@ -0,0 +1,228 @@
+use std::{future::Future, time::Duration};
+
+use crate::api::StationsError;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(10);
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+const DEFAULT_JITTER: f64 = 0.2;
+
+/// Retry policy applied to every [`AlertClient`](crate::api::AlertClient) request.
+///
+/// The delay before retry `n` is `initial_backoff * multiplier^(n - 1)`, capped at
+/// `max_backoff` and randomly spread by `±jitter` of its value.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    retryable: fn(&StationsError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            multiplier: DEFAULT_MULTIPLIER,
+            jitter: DEFAULT_JITTER,
+            retryable: StationsError::is_transient,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that performs a single attempt.
+    pub fn none() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Total number of attempts, including the first one.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        if multiplier.is_finite() && multiplier >= 1.0 {
+            self.multiplier = multiplier;
+        }
+        self
+    }
+
+    /// Fraction of the delay used as random spread, clamped to `0.0..=1.0`.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        if jitter.is_finite() {
+            self.jitter = jitter.clamp(0.0, 1.0);
+        }
+        self
+    }
+
+    /// Decides which errors are worth another attempt, [`StationsError::is_transient`] by default.
+    pub fn retry_if(mut self, retryable: fn(&StationsError) -> bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    /// Delay before the given retry, starting from `1` for the second attempt.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let base = (self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent))
+            .min(self.max_backoff.as_secs_f64());
+        let spread = 1.0 + self.jitter * (2.0 * fastrand::f64() - 1.0);
+        Duration::from_secs_f64((base * spread).max(0.0))
+    }
+
+    pub(crate) async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T, StationsError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, StationsError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(error) if attempt < self.max_attempts && (self.retryable)(&error) => {
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(error) if attempt > 1 => {
+                    return Err(StationsError::Retried {
+                        attempts: attempt,
+                        source: Box::new(error),
+                    });
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn failing(attempts: &Cell<u32>) -> impl Future<Output = Result<(), StationsError>> {
+        attempts.set(attempts.get() + 1);
+        async { Err(StationsError::Unknown("boom".into())) }
+    }
+
+    #[test]
+    fn backoff_grows_and_is_capped() {
+        let policy = RetryPolicy::default()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(350))
+            .multiplier(2.0)
+            .jitter(0.0);
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(350));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn backoff_stays_within_jitter() {
+        let policy = RetryPolicy::default()
+            .initial_backoff(Duration::from_secs(1))
+            .jitter(0.2);
+
+        for _ in 0..100 {
+            let backoff = policy.backoff(1);
+            assert!((Duration::from_millis(800)..=Duration::from_millis(1200)).contains(&backoff));
+        }
+    }
+
+    #[test]
+    fn setters_reject_invalid_values() {
+        let policy = RetryPolicy::default()
+            .max_attempts(0)
+            .multiplier(0.5)
+            .multiplier(f64::NAN)
+            .jitter(3.0);
+
+        assert_eq!(policy.max_attempts, 1);
+        assert_eq!(policy.multiplier, DEFAULT_MULTIPLIER);
+        assert_eq!(policy.jitter, 1.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_until_attempts_run_out() {
+        let attempts = Cell::new(0);
+        let policy = RetryPolicy::default().max_attempts(4).retry_if(|_| true);
+
+        let error = policy.run(|| failing(&attempts)).await.unwrap_err();
+
+        assert_eq!(attempts.get(), 4);
+        assert_eq!(error.attempts(), 4);
+        assert!(matches!(
+            error,
+            StationsError::Retried { source, .. } if matches!(*source, StationsError::Unknown(_))
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_at_first_success() {
+        let attempts = Cell::new(0);
+        let policy = RetryPolicy::default().retry_if(|_| true);
+
+        let value = policy
+            .run(|| {
+                attempts.set(attempts.get() + 1);
+                let attempt = attempts.get();
+                async move {
+                    match attempt {
+                        1 => Err(StationsError::Unknown("boom".into())),
+                        _ => Ok(attempt),
+                    }
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(value, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn does_not_retry_permanent_errors() {
+        let attempts = Cell::new(0);
+
+        let error = RetryPolicy::default()
+            .run(|| failing(&attempts))
+            .await
+            .unwrap_err();
+
+        assert_eq!(attempts.get(), 1);
+        assert!(matches!(error, StationsError::Unknown(_)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn single_attempt_is_not_wrapped() {
+        let attempts = Cell::new(0);
+
+        let error = RetryPolicy::none()
+            .retry_if(|_| true)
+            .run(|| failing(&attempts))
+            .await
+            .unwrap_err();
+
+        assert_eq!(attempts.get(), 1);
+        assert_eq!(error.attempts(), 1);
+    }
+}
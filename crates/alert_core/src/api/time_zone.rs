@@ -0,0 +1,96 @@
+use chrono::{DateTime, LocalResult, NaiveDateTime, Offset as _, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+
+/// Zone of the regional bulletins: upstream slots are read in Europe/Rome unless told otherwise.
+pub const STATION_TIME_ZONE: Tz = chrono_tz::Europe::Rome;
+
+/// Reads a wall clock time in `zone` without failing around DST changes.
+///
+/// A time repeated when clocks go back resolves to its first occurrence; a time skipped when
+/// clocks go forward is shifted forward by the length of the gap (02:30 becomes 03:30).
+pub fn resolve_local_time<T>(zone: &T, naive: &NaiveDateTime) -> DateTime<T>
+where
+    T: TimeZone,
+{
+    match zone.from_local_datetime(naive) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time,
+        LocalResult::None => {
+            // DST gaps are far less than a day apart, so a day earlier the offset in effect is
+            // the one from before the gap.
+            let offset = zone
+                .offset_from_utc_datetime(&(*naive - TimeDelta::days(1)))
+                .fix();
+            zone.from_utc_datetime(&(*naive - offset))
+        }
+    }
+}
+
+/// Parses `input` with `format` as a wall clock time in `zone`, see [`resolve_local_time`].
+pub fn parse_local_time<T>(
+    zone: &T,
+    input: &str,
+    format: &str,
+) -> Result<DateTime<T>, chrono::ParseError>
+where
+    T: TimeZone,
+{
+    let naive = NaiveDateTime::parse_from_str(input.trim(), format)?;
+    Ok(resolve_local_time(zone, &naive))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    const FORMAT: &str = "%Y-%m-%d %H:%M";
+
+    fn rome(input: &str) -> DateTime<Tz> {
+        parse_local_time(&STATION_TIME_ZONE, input, FORMAT).unwrap()
+    }
+
+    #[test]
+    fn regular_times_keep_their_offset() {
+        assert_eq!(
+            rome("2024-01-15 12:00").to_rfc3339(),
+            "2024-01-15T12:00:00+01:00"
+        );
+        assert_eq!(
+            rome(" 2024-07-15 12:00 ").to_rfc3339(),
+            "2024-07-15T12:00:00+02:00"
+        );
+    }
+
+    #[test]
+    fn skipped_times_move_forward_by_the_gap() {
+        // Clocks went from 02:00 to 03:00 on 31 March 2024.
+        assert_eq!(
+            rome("2024-03-31 02:30").to_rfc3339(),
+            "2024-03-31T03:30:00+02:00"
+        );
+    }
+
+    #[test]
+    fn repeated_times_resolve_to_the_first_occurrence() {
+        // Clocks went from 03:00 back to 02:00 on 27 October 2024.
+        let time = rome("2024-10-27 02:30");
+        assert_eq!(time.to_rfc3339(), "2024-10-27T02:30:00+02:00");
+        assert_eq!(
+            time.to_utc(),
+            "2024-10-27T00:30:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        assert!(parse_local_time(&STATION_TIME_ZONE, "2024-13-01 00:00", FORMAT).is_err());
+        assert!(parse_local_time(&STATION_TIME_ZONE, "yesterday", FORMAT).is_err());
+    }
+
+    #[test]
+    fn fixed_zones_are_never_ambiguous() {
+        let naive = NaiveDateTime::parse_from_str("2024-03-31 02:30", FORMAT).unwrap();
+        assert_eq!(resolve_local_time(&Utc, &naive).naive_utc(), naive);
+    }
+}
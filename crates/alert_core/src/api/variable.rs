@@ -0,0 +1,107 @@
+use std::{borrow::Cow, fmt};
+
+use serde::{Deserialize, Serialize};
+
+/// Sensor variable served by the upstream API.
+///
+/// The code is the `variabile` query parameter: a level/time-range prefix followed by the BUFR
+/// descriptor of the measured quantity (`B13215` is the water level, `B13011` the precipitation,
+/// `B12101` the air temperature).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Variable {
+    code: Cow<'static, str>,
+    name: Cow<'static, str>,
+    unit: Cow<'static, str>,
+}
+
+impl Variable {
+    pub const HYDROMETRIC_LEVEL: Variable =
+        Variable::from_static("254,0,0/1,-,-,-/B13215", "Livello idrometrico", "m");
+    pub const PRECIPITATION: Variable =
+        Variable::from_static("1,0,900/1,-,-,-/B13011", "Precipitazione", "mm");
+    pub const TEMPERATURE: Variable =
+        Variable::from_static("254,0,0/103,2000,-,-/B12101", "Temperatura", "°C");
+
+    pub const KNOWN: [Variable; 3] = [
+        Variable::HYDROMETRIC_LEVEL,
+        Variable::PRECIPITATION,
+        Variable::TEMPERATURE,
+    ];
+
+    pub fn new(code: impl Into<String>, name: impl Into<String>, unit: impl Into<String>) -> Self {
+        Self {
+            code: Cow::Owned(code.into()),
+            name: Cow::Owned(name.into()),
+            unit: Cow::Owned(unit.into()),
+        }
+    }
+
+    const fn from_static(code: &'static str, name: &'static str, unit: &'static str) -> Self {
+        Self {
+            code: Cow::Borrowed(code),
+            name: Cow::Borrowed(name),
+            unit: Cow::Borrowed(unit),
+        }
+    }
+
+    /// Looks up one of the [`Variable::KNOWN`] variables by its upstream code.
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::KNOWN
+            .into_iter()
+            .find(|variable| variable.code() == code)
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn unit(&self) -> &str {
+        &self.unit
+    }
+}
+
+impl Default for Variable {
+    fn default() -> Self {
+        Self::HYDROMETRIC_LEVEL
+    }
+}
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_known_codes() {
+        for variable in Variable::KNOWN {
+            assert_eq!(Variable::from_code(variable.code()), Some(variable.clone()));
+        }
+        assert_eq!(Variable::from_code("254,0,0/1,-,-,-/B99999"), None);
+        assert_eq!(Variable::default(), Variable::HYDROMETRIC_LEVEL);
+    }
+
+    #[test]
+    fn custom_variables_compare_by_value() {
+        let custom = Variable::new("1,0,900/1,-,-,-/B13011", "Precipitazione", "mm");
+        assert_eq!(custom, Variable::PRECIPITATION);
+        assert_eq!(custom.to_string(), "Precipitazione (mm)");
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let json = serde_json::to_string(&Variable::TEMPERATURE).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Variable>(&json).unwrap(),
+            Variable::TEMPERATURE
+        );
+    }
+}
@@ -1,2 +1,5 @@
+pub mod analytics;
 pub mod api;
 pub mod model;
+pub mod source;
+pub mod storage;
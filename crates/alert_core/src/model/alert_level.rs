@@ -0,0 +1,71 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Alert level of a station reading, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertLevel {
+    NoData,
+    /// A reading is available but the station has no thresholds configured.
+    NoThresholds,
+    Normal,
+    Level1,
+    Level2,
+    Level3,
+}
+
+impl AlertLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlertLevel::NoData => "No data",
+            AlertLevel::NoThresholds => "No thresholds",
+            AlertLevel::Normal => "Normal",
+            AlertLevel::Level1 => "Threshold 1",
+            AlertLevel::Level2 => "Threshold 2",
+            AlertLevel::Level3 => "Threshold 3",
+        }
+    }
+}
+
+impl fmt::Display for AlertLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordered_by_severity() {
+        let levels = [
+            AlertLevel::NoData,
+            AlertLevel::NoThresholds,
+            AlertLevel::Normal,
+            AlertLevel::Level1,
+            AlertLevel::Level2,
+            AlertLevel::Level3,
+        ];
+        assert!(levels.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn serializes_in_snake_case() {
+        assert_eq!(
+            serde_json::to_string(&AlertLevel::NoThresholds).unwrap(),
+            "\"no_thresholds\""
+        );
+        assert_eq!(
+            serde_json::from_str::<AlertLevel>("\"level2\"").unwrap(),
+            AlertLevel::Level2
+        );
+    }
+
+    #[test]
+    fn displays_its_name() {
+        assert_eq!(AlertLevel::Level2.to_string(), "Threshold 2");
+        assert_eq!(AlertLevel::NoData.to_string(), "No data");
+    }
+}
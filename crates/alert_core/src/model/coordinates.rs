@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use crate::api::StationsError;
+
+/// Upstream sends both axes as WGS84 decimal degrees in fixed point with five decimals,
+/// so `"987258"` is `9.87258°`.
+const FIXED_POINT_SCALE: f64 = 100_000.0;
+const EARTH_RADIUS_KM: f64 = 6_371.008_8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Coordinates {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl Coordinates {
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self, StationsError> {
+        if !(-90.0..=90.0).contains(&latitude) {
+            return Err(StationsError::InvalidCoordinate {
+                axis: "lat",
+                value: latitude.to_string(),
+            });
+        }
+        if !(-180.0..=180.0).contains(&longitude) {
+            return Err(StationsError::InvalidCoordinate {
+                axis: "lon",
+                value: longitude.to_string(),
+            });
+        }
+
+        Ok(Self {
+            latitude,
+            longitude,
+        })
+    }
+
+    /// Parses the raw `lon`/`lat` strings returned by the upstream API.
+    pub fn from_upstream(lon: &str, lat: &str) -> Result<Self, StationsError> {
+        let longitude = parse_fixed_point("lon", lon)?;
+        let latitude = parse_fixed_point("lat", lat)?;
+        Self::new(latitude, longitude)
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    /// Great-circle distance in kilometres, using the haversine formula.
+    pub fn distance_km(&self, other: &Coordinates) -> f64 {
+        let lat1 = self.latitude.to_radians();
+        let lat2 = other.latitude.to_radians();
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+fn parse_fixed_point(axis: &'static str, raw: &str) -> Result<f64, StationsError> {
+    raw.trim()
+        .parse::<i32>()
+        .map(|value| f64::from(value) / FIXED_POINT_SCALE)
+        .map_err(|_| StationsError::InvalidCoordinate {
+            axis,
+            value: raw.to_owned(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_upstream_fixed_point() {
+        let coordinates = Coordinates::from_upstream("1129579", " 4472121 ").unwrap();
+        assert_eq!(coordinates.longitude(), 11.29579);
+        assert_eq!(coordinates.latitude(), 44.72121);
+    }
+
+    #[test]
+    fn rejects_garbage_and_out_of_range() {
+        assert!(matches!(
+            Coordinates::from_upstream("11.2", "4472121"),
+            Err(StationsError::InvalidCoordinate { axis: "lon", .. })
+        ));
+        assert!(matches!(
+            Coordinates::from_upstream("1129579", "9100000"),
+            Err(StationsError::InvalidCoordinate { axis: "lat", .. })
+        ));
+        assert!(Coordinates::new(0.0, 180.5).is_err());
+        assert!(Coordinates::new(-90.0, -180.0).is_ok());
+    }
+
+    #[test]
+    fn distance_along_the_equator() {
+        let origin = Coordinates::new(0.0, 0.0).unwrap();
+        let east = Coordinates::new(0.0, 1.0).unwrap();
+        assert!((origin.distance_km(&east) - 111.195).abs() < 0.001);
+        assert_eq!(origin.distance_km(&origin), 0.0);
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{
+    api::Variable,
+    model::{AlertLevel, Station, StationId, Stations, Thresholds},
+};
+
+/// What changed between two snapshots, from the earlier to the later one.
+///
+/// Stations are matched by [`StationId`]; the ones present in both snapshots are only listed in
+/// [`StationsDiff::changed`] when their value, alert level or thresholds differ.
+///
+/// Unlike [`Stations`], the diff serializes the variable of the snapshots, taken from the later
+/// one, so that exported values keep their unit.
+#[derive(Debug, Clone, Serialize)]
+pub struct StationsDiff {
+    variable: Variable,
+    changed: Vec<StationChange>,
+    appeared: Vec<Station>,
+    disappeared: Vec<Station>,
+}
+
+impl StationsDiff {
+    pub(crate) fn new(before: &Stations, after: &Stations) -> Self {
+        let before_by_id = before
+            .iter()
+            .map(|station| (station.idstazione(), station))
+            .collect::<HashMap<_, _>>();
+        let after_by_id = after
+            .iter()
+            .map(|station| (station.idstazione(), station))
+            .collect::<HashMap<_, _>>();
+
+        let changed = after
+            .iter()
+            .filter_map(|station| {
+                let previous = before_by_id.get(station.idstazione())?;
+                StationChange::new(previous, station)
+            })
+            .collect();
+        let appeared = after
+            .iter()
+            .filter(|station| !before_by_id.contains_key(station.idstazione()))
+            .cloned()
+            .collect();
+        let disappeared = before
+            .iter()
+            .filter(|station| !after_by_id.contains_key(station.idstazione()))
+            .cloned()
+            .collect();
+
+        Self {
+            variable: after.variable().clone(),
+            changed,
+            appeared,
+            disappeared,
+        }
+    }
+
+    pub fn variable(&self) -> &Variable {
+        &self.variable
+    }
+
+    /// Stations present in both snapshots that changed, in the order of the later snapshot.
+    pub fn changed(&self) -> &[StationChange] {
+        &self.changed
+    }
+
+    /// Stations only present in the later snapshot.
+    pub fn appeared(&self) -> &[Station] {
+        &self.appeared
+    }
+
+    /// Stations only present in the earlier snapshot.
+    pub fn disappeared(&self) -> &[Station] {
+        &self.disappeared
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.appeared.is_empty() && self.disappeared.is_empty()
+    }
+
+    /// Changes where the alert level went up.
+    pub fn escalations(&self) -> impl Iterator<Item = &StationChange> {
+        self.changed.iter().filter(|change| {
+            change
+                .level()
+                .is_some_and(|level| level.direction() == Direction::Up)
+        })
+    }
+}
+
+/// Change of a station present in both snapshots.
+#[derive(Debug, Clone, Serialize)]
+pub struct StationChange {
+    idstazione: StationId,
+    nomestaz: String,
+    before: Option<f32>,
+    after: Option<f32>,
+    delta: Option<f32>,
+    level: Option<LevelTransition>,
+    thresholds: Option<ThresholdsChange>,
+}
+
+impl StationChange {
+    fn new(before: &Station, after: &Station) -> Option<Self> {
+        let value_before = before.value().copied();
+        let value_after = after.value().copied();
+
+        let level = LevelTransition::new(before.alert_level(), after.alert_level());
+        let thresholds = (before.thresholds() != after.thresholds()).then(|| ThresholdsChange {
+            before: *before.thresholds(),
+            after: *after.thresholds(),
+        });
+        if value_before == value_after && level.is_none() && thresholds.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            idstazione: after.idstazione().clone(),
+            nomestaz: after.nomestaz().to_owned(),
+            before: value_before,
+            after: value_after,
+            delta: value_before
+                .zip(value_after)
+                .map(|(before, after)| after - before),
+            level,
+            thresholds,
+        })
+    }
+
+    pub fn idstazione(&self) -> &StationId {
+        &self.idstazione
+    }
+
+    pub fn nomestaz(&self) -> &str {
+        &self.nomestaz
+    }
+
+    pub fn before(&self) -> Option<f32> {
+        self.before
+    }
+
+    pub fn after(&self) -> Option<f32> {
+        self.after
+    }
+
+    /// Difference between the two values, `None` when either reading is missing.
+    pub fn delta(&self) -> Option<f32> {
+        self.delta
+    }
+
+    /// Alert level change, `None` when the level is the same.
+    pub fn level(&self) -> Option<&LevelTransition> {
+        self.level.as_ref()
+    }
+
+    /// Threshold change, `None` when the thresholds are the same.
+    pub fn thresholds(&self) -> Option<&ThresholdsChange> {
+        self.thresholds.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Up,
+    Down,
+    /// Either side has no reading or no thresholds, so the two levels can't be compared: a
+    /// sensor outage is neither an improvement nor an escalation.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LevelTransition {
+    from: AlertLevel,
+    to: AlertLevel,
+    direction: Direction,
+}
+
+impl LevelTransition {
+    fn new(from: AlertLevel, to: AlertLevel) -> Option<Self> {
+        let unranked = |level| matches!(level, AlertLevel::NoData | AlertLevel::NoThresholds);
+        let direction = match from.cmp(&to) {
+            std::cmp::Ordering::Equal => return None,
+            _ if unranked(from) || unranked(to) => Direction::Unknown,
+            std::cmp::Ordering::Less => Direction::Up,
+            std::cmp::Ordering::Greater => Direction::Down,
+        };
+        Some(Self {
+            from,
+            to,
+            direction,
+        })
+    }
+
+    pub fn from(&self) -> AlertLevel {
+        self.from
+    }
+
+    pub fn to(&self) -> AlertLevel {
+        self.to
+    }
+
+    /// Whether the level went up or down, following the [`AlertLevel`] ordering, or
+    /// [`Direction::Unknown`] when either level isn't an actual alert level.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ThresholdsChange {
+    before: Thresholds,
+    after: Thresholds,
+}
+
+impl ThresholdsChange {
+    pub fn before(&self) -> &Thresholds {
+        &self.before
+    }
+
+    pub fn after(&self) -> &Thresholds {
+        &self.after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::fixtures::{info, station};
+
+    fn ids(stations: &[Station]) -> Vec<&str> {
+        stations
+            .iter()
+            .map(|station| station.idstazione().as_str())
+            .collect()
+    }
+
+    #[test]
+    fn matches_stations_by_id() {
+        let before = Stations::new(vec![
+            station("-/1,1/a", Some(1.0), 2.0),
+            station("-/2,2/a", Some(1.0), 2.0),
+            station("-/3,3/a", Some(1.0), 2.0),
+        ]);
+        let after = Stations::new(vec![
+            station("-/4,4/a", None, 2.0),
+            station("-/3,3/a", Some(2.5), 2.0),
+            station("-/1,1/a", Some(1.0), 2.0),
+        ]);
+
+        let diff = before.diff(&after);
+
+        assert_eq!(ids(diff.appeared()), ["-/4,4/a"]);
+        assert_eq!(ids(diff.disappeared()), ["-/2,2/a"]);
+        assert_eq!(diff.changed().len(), 1);
+        let change = &diff.changed()[0];
+        assert_eq!(change.idstazione().as_str(), "-/3,3/a");
+        assert_eq!(
+            (change.before(), change.after(), change.delta()),
+            (Some(1.0), Some(2.5), Some(1.5))
+        );
+        assert_eq!(
+            change.level().map(LevelTransition::to),
+            Some(AlertLevel::Level1)
+        );
+        assert_eq!(diff.escalations().count(), 1);
+    }
+
+    #[test]
+    fn threshold_and_missing_value_changes_are_reported() {
+        let before = Stations::new(vec![station("-/1,1/a", Some(1.0), 2.0)]);
+        let after = Stations::new(vec![station("-/1,1/a", None, 3.0)]);
+
+        let diff = before.diff(&after);
+
+        let change = &diff.changed()[0];
+        assert_eq!(change.delta(), None);
+        assert_eq!(
+            change.level().map(LevelTransition::direction),
+            Some(Direction::Unknown)
+        );
+        assert_eq!(
+            change.thresholds().map(|t| t.after().soglia1()),
+            Some(Some(3.0))
+        );
+        assert_eq!(diff.escalations().count(), 0);
+    }
+
+    #[test]
+    fn outages_have_no_direction() {
+        let thresholds = Thresholds::new(Some(1.0), Some(2.0), Some(3.0));
+        let info = info("-/1,1/a", 9999, thresholds);
+        let alarm = Stations::new(vec![Station::from_parts(info.clone(), Some(9.0))]);
+        let outage = Stations::new(vec![Station::from_parts(info, None)]);
+
+        let lost = alarm.diff(&outage);
+        let back = outage.diff(&alarm);
+
+        let level = lost.changed()[0].level().unwrap();
+        assert_eq!(
+            (level.from(), level.to(), level.direction()),
+            (AlertLevel::Level3, AlertLevel::NoData, Direction::Unknown)
+        );
+        assert_eq!(
+            back.changed()[0].level().map(LevelTransition::direction),
+            Some(Direction::Unknown)
+        );
+        assert_eq!(lost.escalations().count() + back.escalations().count(), 0);
+    }
+
+    #[test]
+    fn identical_snapshots_have_an_empty_diff() {
+        let stations = Stations::new(vec![station("-/1,1/a", Some(1.0), 2.0)]);
+        assert!(stations.diff(&stations).is_empty());
+    }
+
+    #[test]
+    fn serializes_the_variable() {
+        let variable = Variable::KNOWN[1].clone();
+        let before = Stations::new(Vec::new());
+        let after =
+            Stations::new(vec![station("-/1,1/a", Some(1.0), 2.0)]).with_variable(variable.clone());
+
+        let diff = before.diff(&after);
+        let json = serde_json::to_value(&diff).unwrap();
+
+        assert_eq!(diff.variable(), &variable);
+        assert_eq!(json["variable"], serde_json::to_value(&variable).unwrap());
+        assert_eq!(json["appeared"][0]["value"], 1.0);
+    }
+}
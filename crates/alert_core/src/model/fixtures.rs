@@ -0,0 +1,38 @@
+//! Stations shared by the tests of the model and of its consumers.
+
+use crate::model::{Station, StationInfo, Stations, Thresholds};
+
+/// Id of the stations built by [`stations`].
+pub(crate) const ID: &str = "-/1,2/simnbo";
+
+/// Thresholds with only the first level set.
+pub(crate) fn soglia1(value: f32) -> Thresholds {
+    Thresholds::new(Some(value), None, None)
+}
+
+/// Metadata of a station named `Test` at placeholder coordinates.
+pub(crate) fn info(id: &str, ordinamento: usize, thresholds: Thresholds) -> StationInfo {
+    StationInfo::new(
+        id.parse().unwrap(),
+        ordinamento,
+        "Test",
+        "1",
+        "2",
+        thresholds,
+    )
+}
+
+/// Unordered station reading `value`, with `soglia1` as its only threshold.
+pub(crate) fn station(id: &str, value: Option<f32>, soglia1: f32) -> Station {
+    Station::from_parts(info(id, 9999, self::soglia1(soglia1)), value)
+}
+
+/// Snapshot with a station [`ID`] for each of `values`, with its first threshold at `1.0`.
+pub(crate) fn stations(values: &[Option<f32>]) -> Stations {
+    Stations::new(
+        values
+            .iter()
+            .map(|&value| station(ID, value, 1.0))
+            .collect(),
+    )
+}
@@ -1,62 +1,94 @@
-use serde::{Deserialize, Deserializer, Serialize, de};
+mod alert_level;
+mod coordinates;
+mod diff;
+#[cfg(test)]
+pub(crate) mod fixtures;
+mod reading;
+mod registry;
+mod response;
+mod station_id;
+mod station_info;
+mod thresholds;
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
 use serde_json::Value;
 use serde_with::{VecSkipError, serde_as};
 
+use crate::api::{StationsError, Variable};
+pub use crate::model::{
+    alert_level::AlertLevel,
+    coordinates::Coordinates,
+    diff::{Direction, LevelTransition, StationChange, StationsDiff, ThresholdsChange},
+    reading::Reading,
+    registry::StationRegistry,
+    response::{RejectedStation, StationsResponse},
+    station_id::StationId,
+    station_info::StationInfo,
+    thresholds::Thresholds,
+};
+
+/// A station together with its reading in a snapshot.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Station {
-    idstazione: String,
-    ordinamento: usize,
-    nomestaz: String,
-    lon: String,
-    lat: String,
+    #[serde(flatten)]
+    info: StationInfo,
     value: Option<f32>,
-    soglia1: f32,
-    soglia2: f32,
-    soglia3: f32,
 }
 
 impl Station {
-    pub fn idstazione(&self) -> &str {
-        &self.idstazione
+    pub fn from_parts(info: StationInfo, value: Option<f32>) -> Self {
+        Self { info, value }
+    }
+
+    /// Splits the station into its metadata and the reading it had at `time`.
+    pub fn into_parts<T>(self, time: DateTime<T>) -> (StationInfo, Reading)
+    where
+        T: TimeZone,
+    {
+        let reading = Reading::new(self.info.idstazione().clone(), time.to_utc(), self.value);
+        (self.info, reading)
+    }
+
+    pub fn info(&self) -> &StationInfo {
+        &self.info
+    }
+
+    pub fn idstazione(&self) -> &StationId {
+        self.info.idstazione()
     }
 
     pub fn nomestaz(&self) -> &str {
-        &self.nomestaz
+        self.info.nomestaz()
+    }
+
+    pub fn coordinates(&self) -> Result<Coordinates, StationsError> {
+        self.info.coordinates()
     }
 
     pub fn value(&self) -> Option<&f32> {
         self.value.as_ref()
     }
-    pub fn soglia1(&self) -> &f32 {
-        &self.soglia1
+    pub fn thresholds(&self) -> &Thresholds {
+        self.info.thresholds()
     }
-    pub fn soglia2(&self) -> &f32 {
-        &self.soglia2
+    pub fn soglia1(&self) -> Option<f32> {
+        self.thresholds().soglia1()
     }
-    pub fn soglia3(&self) -> &f32 {
-        &self.soglia3
+    pub fn soglia2(&self) -> Option<f32> {
+        self.thresholds().soglia2()
     }
-    fn score(&self) -> u8 {
-        if self.value.is_none() {
-            return 0;
-        }
-        let value = self.value.unwrap();
-        if value > self.soglia3 {
-            return 0b1000;
-        }
-        if value > self.soglia2 {
-            return 0b0100;
-        }
-        if value > self.soglia1 {
-            return 0b0010;
-        }
-        0
+    pub fn soglia3(&self) -> Option<f32> {
+        self.thresholds().soglia3()
+    }
+    pub fn alert_level(&self) -> AlertLevel {
+        self.info.alert_level(self.value)
     }
 }
 
 impl PartialEq for Station {
     fn eq(&self, other: &Self) -> bool {
-        self.idstazione == other.idstazione
+        self.idstazione() == other.idstazione()
     }
 }
 
@@ -70,7 +102,7 @@ impl PartialOrd for Station {
 
 impl Ord for Station {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        let mut out = self.score().cmp(&other.score());
+        let mut out = self.alert_level().cmp(&other.alert_level());
         if matches!(out, std::cmp::Ordering::Equal) {
             out = self
                 .value
@@ -81,85 +113,214 @@ impl Ord for Station {
     }
 }
 
+/// Stations snapshot for a single variable.
+///
+/// Serializes as the plain upstream array, so the variable, unit included, is lost: it defaults
+/// to [`Variable::HYDROMETRIC_LEVEL`] when deserializing. Whatever persists or exports a
+/// snapshot has to record the variable next to it, as [`StationsDiff`] and
+/// [`JsonDirStore`](crate::storage::JsonDirStore) do.
 #[serde_as]
 #[derive(Deserialize, Serialize, Clone)]
-pub struct Stations(#[serde_as(as = "VecSkipError<_>")] Vec<Station>);
+#[serde(transparent)]
+pub struct Stations {
+    #[serde_as(as = "VecSkipError<_>")]
+    stations: Vec<Station>,
+    #[serde(skip)]
+    variable: Variable,
+}
 
 impl Stations {
     pub fn new(stations: Vec<Station>) -> Self {
-        Self(stations)
+        Self {
+            stations,
+            variable: Variable::default(),
+        }
+    }
+
+    pub fn with_variable(mut self, variable: Variable) -> Self {
+        self.variable = variable;
+        self
+    }
+
+    pub fn variable(&self) -> &Variable {
+        &self.variable
     }
 
     pub fn iter(&self) -> std::slice::Iter<'_, Station> {
-        self.0.iter()
+        self.stations.iter()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.stations.is_empty()
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.stations.len()
     }
 
     pub fn into_vec(self) -> Vec<Station> {
-        self.0
+        self.stations
+    }
+
+    /// Splits the snapshot, taken at `time`, into station metadata and readings.
+    pub fn split<T>(&self, time: DateTime<T>) -> (StationRegistry, Vec<Reading>)
+    where
+        T: TimeZone,
+    {
+        let mut registry = StationRegistry::new();
+        let readings = registry.register(self, time);
+        (registry, readings)
+    }
+
+    /// Changes going from this snapshot to `other`, a later one.
+    pub fn diff(&self, other: &Stations) -> StationsDiff {
+        StationsDiff::new(self, other)
     }
 
     pub fn sort_by_alert_desc(&mut self) {
-        self.0.sort_by(|a, b| b.cmp(a));
+        self.stations.sort_by(|a, b| b.cmp(a));
     }
 }
 
 impl AsRef<[Station]> for Stations {
     fn as_ref(&self) -> &[Station] {
-        &self.0
+        &self.stations
     }
 }
 
-fn de_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
-    Ok(match Value::deserialize(deserializer)? {
+/// Upstream timestamps are unsigned milliseconds since the Unix epoch; anything chrono can't
+/// represent is rejected instead of wrapping around.
+fn time_from_millis(millis: u64) -> Option<DateTime<Utc>> {
+    i64::try_from(millis)
+        .ok()
+        .and_then(DateTime::from_timestamp_millis)
+}
+
+fn de_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    let millis = match Value::deserialize(deserializer)? {
         Value::Number(num) => num.as_u64().ok_or(de::Error::custom("Invalid number"))?,
         Value::String(s) => s.parse::<u64>().map_err(de::Error::custom)?,
         _ => return Err(de::Error::custom("wrong type")),
-    })
+    };
+    time_from_millis(millis)
+        .ok_or_else(|| de::Error::custom(format!("timestamp out of range: {millis}")))
+}
+
+fn ser_timestamp<S: Serializer>(time: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i64(time.timestamp_millis())
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TimeValue {
-    #[serde(deserialize_with = "de_timestamp")]
-    t: u64,
+    #[serde(deserialize_with = "de_timestamp", serialize_with = "ser_timestamp")]
+    t: DateTime<Utc>,
     v: Option<f64>,
 }
 
 impl TimeValue {
-    pub fn timestamp(&self) -> u64 {
+    /// A reading at `time`, which can't be before the Unix epoch.
+    pub fn new(time: DateTime<Utc>, value: Option<f64>) -> Result<Self, StationsError> {
+        if time < DateTime::UNIX_EPOCH {
+            return Err(StationsError::InvalidTimestamp(time.to_rfc3339()));
+        }
+        Ok(Self { t: time, v: value })
+    }
+
+    /// A reading at `millis` milliseconds since the Unix epoch, as sent by upstream.
+    pub fn from_millis(millis: u64, value: Option<f64>) -> Result<Self, StationsError> {
+        let time =
+            time_from_millis(millis).ok_or(StationsError::InvalidTimestamp(millis.to_string()))?;
+        Ok(Self { t: time, v: value })
+    }
+
+    pub fn time(&self) -> DateTime<Utc> {
         self.t
     }
 
+    /// Milliseconds since the Unix epoch.
+    pub fn timestamp(&self) -> u64 {
+        // Never negative, see the constructors.
+        self.t.timestamp_millis() as u64
+    }
+
     pub fn value(&self) -> Option<f64> {
         self.v
     }
 }
 
+/// Time series of a single station and variable.
+///
+/// Serializes as the plain upstream array, so like [`Stations`] it loses its variable and unit,
+/// which default to [`Variable::HYDROMETRIC_LEVEL`] when deserializing.
 #[derive(Deserialize, Serialize, Clone)]
-pub struct TimeSeries(Vec<TimeValue>);
+#[serde(transparent)]
+pub struct TimeSeries {
+    values: Vec<TimeValue>,
+    #[serde(skip)]
+    variable: Variable,
+}
 
 impl TimeSeries {
     pub fn new(data: Vec<TimeValue>) -> Self {
-        Self(data)
+        Self {
+            values: data,
+            variable: Variable::default(),
+        }
+    }
+
+    pub fn with_variable(mut self, variable: Variable) -> Self {
+        self.variable = variable;
+        self
+    }
+
+    pub fn variable(&self) -> &Variable {
+        &self.variable
     }
 
     pub fn iter(&self) -> std::slice::Iter<'_, TimeValue> {
-        self.0.iter()
+        self.values.iter()
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.values.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.values.is_empty()
+    }
+
+    /// Readings from `start` to `end`, both included.
+    pub fn between<T>(&self, start: DateTime<T>, end: DateTime<T>) -> Self
+    where
+        T: TimeZone,
+    {
+        let (start, end) = (start.to_utc(), end.to_utc());
+        self.filter(|value| (start..=end).contains(&value.time()))
+    }
+
+    /// Readings in the `duration` leading up to the most recent one, included.
+    pub fn last(&self, duration: TimeDelta) -> Self {
+        match self.values.iter().map(TimeValue::time).max() {
+            Some(end) => {
+                let start = end
+                    .checked_sub_signed(duration)
+                    .unwrap_or(DateTime::<Utc>::MIN_UTC);
+                self.between(start, end)
+            }
+            None => self.clone(),
+        }
+    }
+
+    fn filter(&self, predicate: impl Fn(&TimeValue) -> bool) -> Self {
+        Self {
+            values: self
+                .values
+                .iter()
+                .filter(|value| predicate(value))
+                .cloned()
+                .collect(),
+            variable: self.variable.clone(),
+        }
     }
 
     /// Converts the time series into chart points using Unix timestamps in milliseconds on the x axis.
@@ -167,10 +328,153 @@ impl TimeSeries {
     /// The upstream API returns roughly 250 readings spaced 15 minutes apart, with the latest
     /// reading aligned with the current timestamp. Consumers should therefore treat the x axis as
     /// real time and render at least one label per day for readability.
+    ///
+    /// Missing readings are left out rather than replaced, see [`TimeSeries::segments`] to keep
+    /// track of where they were.
     pub fn as_dataset(self) -> Vec<(f64, f64)> {
-        self.0
+        self.segments().into_iter().flatten().collect()
+    }
+
+    /// Chart points like [`TimeSeries::as_dataset`], split as in [`TimeSeries::runs`].
+    pub fn segments(&self) -> Vec<Vec<(f64, f64)>> {
+        self.runs()
             .into_iter()
-            .map(|tv| (tv.timestamp() as f64, tv.value().unwrap_or(0.0)))
+            .map(|run| {
+                run.into_iter()
+                    .map(|(time, value)| (time.timestamp_millis() as f64, value))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Runs of consecutive readings with a value: each missing reading (a sensor outage) ends
+    /// the current run.
+    pub fn runs(&self) -> Vec<Vec<(DateTime<Utc>, f64)>> {
+        let mut runs = Vec::new();
+        let mut current = Vec::new();
+        for tv in &self.values {
+            match tv.value() {
+                Some(value) => current.push((tv.time(), value)),
+                None if !current.is_empty() => runs.push(std::mem::take(&mut current)),
+                None => {}
+            }
+        }
+        if !current.is_empty() {
+            runs.push(current);
+        }
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(values: &[(i64, Option<f64>)]) -> TimeSeries {
+        TimeSeries::new(
+            values
+                .iter()
+                .map(|&(minutes, value)| {
+                    TimeValue::new(DateTime::UNIX_EPOCH + TimeDelta::minutes(minutes), value)
+                        .unwrap()
+                })
+                .collect(),
+        )
+    }
+
+    fn minutes(series: &TimeSeries) -> Vec<i64> {
+        series
+            .iter()
+            .map(|value| (value.time() - DateTime::UNIX_EPOCH).num_minutes())
             .collect()
     }
+
+    #[test]
+    fn time_values_reject_times_before_the_epoch() {
+        assert!(TimeValue::new(DateTime::UNIX_EPOCH - TimeDelta::seconds(1), None).is_err());
+        assert!(TimeValue::from_millis(u64::MAX, None).is_err());
+
+        let value = TimeValue::from_millis(1_700_000_000_000, Some(1.5)).unwrap();
+        assert_eq!(value.timestamp(), 1_700_000_000_000);
+        assert_eq!(value.time().timestamp(), 1_700_000_000);
+    }
+
+    #[test]
+    fn time_values_accept_numbers_and_strings() {
+        let values: Vec<TimeValue> =
+            serde_json::from_str(r#"[{"t": 900000, "v": 1.5}, {"t": "1800000", "v": null}]"#)
+                .unwrap();
+        assert_eq!(values[0].timestamp(), 900_000);
+        assert_eq!(values[1].value(), None);
+
+        assert!(serde_json::from_str::<TimeValue>(r#"{"t": -1, "v": null}"#).is_err());
+        assert!(serde_json::from_str::<TimeValue>(r#"{"t": true, "v": null}"#).is_err());
+
+        let json = serde_json::to_value(&values[0]).unwrap();
+        assert_eq!(json, serde_json::json!({"t": 900000, "v": 1.5}));
+    }
+
+    #[test]
+    fn between_includes_both_ends() {
+        let series = series(&[(0, Some(1.0)), (15, Some(2.0)), (30, None), (45, Some(3.0))]);
+        let start = DateTime::UNIX_EPOCH + TimeDelta::minutes(15);
+
+        assert_eq!(
+            minutes(&series.between(start, start + TimeDelta::minutes(30))),
+            [15, 30, 45]
+        );
+        assert!(
+            series
+                .between(start, start - TimeDelta::minutes(1))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn last_is_relative_to_the_most_recent_reading() {
+        let series = series(&[(30, Some(1.0)), (0, Some(2.0)), (45, None)]);
+
+        assert_eq!(minutes(&series.last(TimeDelta::minutes(15))), [30, 45]);
+        assert_eq!(minutes(&series.last(TimeDelta::max_value())), [30, 0, 45]);
+        assert!(
+            TimeSeries::new(Vec::new())
+                .last(TimeDelta::hours(1))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn slicing_keeps_the_variable() {
+        let variable = Variable::HYDROMETRIC_LEVEL;
+        let series = series(&[(0, Some(1.0))]).with_variable(variable.clone());
+
+        assert_eq!(series.last(TimeDelta::hours(1)).variable(), &variable);
+    }
+
+    #[test]
+    fn missing_readings_split_runs() {
+        let series = series(&[
+            (0, None),
+            (15, Some(1.0)),
+            (30, Some(2.0)),
+            (45, None),
+            (60, None),
+            (75, Some(3.0)),
+        ]);
+
+        let runs = series.runs();
+        let lengths = runs.iter().map(Vec::len).collect::<Vec<_>>();
+        assert_eq!(lengths, [2, 1]);
+        assert_eq!(runs[1][0].1, 3.0);
+
+        let segments = series.segments();
+        assert_eq!(segments[0], [(900_000.0, 1.0), (1_800_000.0, 2.0)]);
+        assert_eq!(series.as_dataset().len(), 3);
+    }
+
+    #[test]
+    fn series_without_values_have_no_runs() {
+        assert!(series(&[(0, None), (15, None)]).runs().is_empty());
+        assert!(TimeSeries::new(Vec::new()).segments().is_empty());
+    }
 }
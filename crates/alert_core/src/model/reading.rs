@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::model::StationId;
+
+/// Value of a station at a slot, without the station metadata, see
+/// [`StationRegistry`](crate::model::StationRegistry).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Reading {
+    station: StationId,
+    time: DateTime<Utc>,
+    value: Option<f32>,
+}
+
+impl Reading {
+    pub fn new(station: StationId, time: DateTime<Utc>, value: Option<f32>) -> Self {
+        Self {
+            station,
+            time,
+            value,
+        }
+    }
+
+    pub fn station(&self) -> &StationId {
+        &self.station
+    }
+
+    pub fn time(&self) -> DateTime<Utc> {
+        self.time
+    }
+
+    pub fn value(&self) -> Option<f32> {
+        self.value
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Reading, Station, StationId, StationInfo, Stations};
+
+/// Metadata of every known station, kept once instead of in each snapshot.
+///
+/// Serializes as an array of [`StationInfo`], in upstream order.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(from = "Vec<StationInfo>", into = "Vec<StationInfo>")]
+pub struct StationRegistry {
+    stations: HashMap<StationId, StationInfo>,
+}
+
+impl StationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces the metadata of a station, returning the previous one if it differs.
+    pub fn insert(&mut self, info: StationInfo) -> Option<StationInfo> {
+        match self.stations.get_mut(info.idstazione()) {
+            Some(current) if *current == info => None,
+            Some(current) => Some(std::mem::replace(current, info)),
+            None => {
+                self.stations.insert(info.idstazione().clone(), info);
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, id: &StationId) -> Option<&StationInfo> {
+        self.stations.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &StationInfo> {
+        self.stations.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty()
+    }
+
+    /// Records the metadata of the stations in a snapshot taken at `time` and returns its
+    /// readings.
+    pub fn register<T>(&mut self, stations: &Stations, time: DateTime<T>) -> Vec<Reading>
+    where
+        T: TimeZone,
+    {
+        let time = time.to_utc();
+        stations
+            .iter()
+            .map(|station| {
+                self.insert(station.info().clone());
+                Reading::new(station.idstazione().clone(), time, station.value().copied())
+            })
+            .collect()
+    }
+
+    /// Joins a reading with the metadata of its station, `None` for an unknown station.
+    pub fn station(&self, reading: &Reading) -> Option<Station> {
+        self.get(reading.station())
+            .map(|info| Station::from_parts(info.clone(), reading.value()))
+    }
+
+    /// Rebuilds a snapshot from readings, skipping the ones of unknown stations.
+    pub fn snapshot<'a>(&self, readings: impl IntoIterator<Item = &'a Reading>) -> Stations {
+        Stations::new(
+            readings
+                .into_iter()
+                .filter_map(|reading| self.station(reading))
+                .collect(),
+        )
+    }
+}
+
+impl From<Vec<StationInfo>> for StationRegistry {
+    fn from(stations: Vec<StationInfo>) -> Self {
+        Self {
+            stations: stations
+                .into_iter()
+                .map(|info| (info.idstazione().clone(), info))
+                .collect(),
+        }
+    }
+}
+
+impl From<StationRegistry> for Vec<StationInfo> {
+    fn from(registry: StationRegistry) -> Self {
+        let mut stations = registry.stations.into_values().collect::<Vec<_>>();
+        stations.sort_by(|a, b| {
+            a.ordinamento()
+                .cmp(&b.ordinamento())
+                .then_with(|| a.idstazione().cmp(b.idstazione()))
+        });
+        stations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::model::fixtures::{info, soglia1};
+
+    #[test]
+    fn insert_reports_changed_metadata_only() {
+        let mut registry = StationRegistry::new();
+
+        assert_eq!(registry.insert(info("-/1,1/a", 1, soglia1(2.0))), None);
+        assert_eq!(registry.insert(info("-/1,1/a", 1, soglia1(2.0))), None);
+        let previous = registry.insert(info("-/1,1/a", 1, soglia1(3.0)));
+
+        assert_eq!(
+            previous.map(|info| info.thresholds().soglia1()),
+            Some(Some(2.0))
+        );
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn split_snapshots_can_be_rebuilt() {
+        let time = Utc::now();
+        let stations = Stations::new(vec![
+            Station::from_parts(info("-/1,1/a", 1, soglia1(2.0)), Some(2.5)),
+            Station::from_parts(info("-/2,2/a", 2, soglia1(2.0)), None),
+        ]);
+
+        let (registry, readings) = stations.split(time);
+        let unknown = Reading::new("-/3,3/a".parse().unwrap(), time, Some(1.0));
+        let rebuilt = registry.snapshot(readings.iter().chain([&unknown]));
+
+        assert_eq!(
+            readings[0],
+            Reading::new("-/1,1/a".parse().unwrap(), time, Some(2.5))
+        );
+        assert_eq!(rebuilt.len(), 2);
+        for (rebuilt, original) in rebuilt.iter().zip(stations.iter()) {
+            assert_eq!(rebuilt.info(), original.info());
+            assert_eq!(rebuilt.value(), original.value());
+        }
+        assert!(registry.station(&unknown).is_none());
+    }
+
+    #[test]
+    fn serializes_in_upstream_order() {
+        let registry = StationRegistry::from(vec![
+            info("-/2,2/a", 9999, soglia1(1.0)),
+            info("-/3,3/a", 1, soglia1(1.0)),
+            info("-/1,1/a", 9999, soglia1(1.0)),
+        ]);
+
+        let json = serde_json::to_value(&registry).unwrap();
+        let ids = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|info| info["idstazione"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["-/3,3/a", "-/1,1/a", "-/2,2/a"]);
+
+        let back: StationRegistry = serde_json::from_value(json).unwrap();
+        assert_eq!(back.len(), 3);
+    }
+}
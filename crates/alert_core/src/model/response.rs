@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::{
+    api::Variable,
+    model::{Station, Stations},
+};
+
+/// Upstream record that couldn't be read as a [`Station`], kept with the reason it was rejected.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RejectedStation {
+    error: String,
+    raw: Value,
+}
+
+impl RejectedStation {
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+
+    pub fn raw(&self) -> &Value {
+        &self.raw
+    }
+}
+
+/// Stations snapshot together with the records dropped while decoding it.
+///
+/// Deserializes from the same array as [`Stations`], but instead of silently skipping the
+/// entries in an unexpected shape it collects them in [`StationsResponse::rejected`].
+#[derive(Clone, Serialize)]
+pub struct StationsResponse {
+    stations: Stations,
+    rejected: Vec<RejectedStation>,
+    #[serde(skip)]
+    waited: Duration,
+}
+
+impl StationsResponse {
+    pub fn new(stations: Stations, rejected: Vec<RejectedStation>) -> Self {
+        Self {
+            stations,
+            rejected,
+            waited: Duration::ZERO,
+        }
+    }
+
+    pub fn from_values(values: Vec<Value>) -> Self {
+        let mut stations = Vec::with_capacity(values.len());
+        let mut rejected = Vec::new();
+
+        for raw in values {
+            match Station::deserialize(&raw) {
+                Ok(station) => stations.push(station),
+                Err(error) => rejected.push(RejectedStation {
+                    error: error.to_string(),
+                    raw,
+                }),
+            }
+        }
+
+        Self::new(Stations::new(stations), rejected)
+    }
+
+    pub fn with_variable(mut self, variable: Variable) -> Self {
+        self.stations = self.stations.with_variable(variable);
+        self
+    }
+
+    pub fn with_waited(mut self, waited: Duration) -> Self {
+        self.waited = waited;
+        self
+    }
+
+    pub fn stations(&self) -> &Stations {
+        &self.stations
+    }
+
+    pub fn stations_mut(&mut self) -> &mut Stations {
+        &mut self.stations
+    }
+
+    pub fn rejected(&self) -> &[RejectedStation] {
+        &self.rejected
+    }
+
+    /// How long the request for this snapshot was held back by the rate limiter, retries
+    /// included.
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+
+    pub fn into_stations(self) -> Stations {
+        self.stations
+    }
+
+    pub fn into_parts(self) -> (Stations, Vec<RejectedStation>) {
+        (self.stations, self.rejected)
+    }
+}
+
+impl<'de> Deserialize<'de> for StationsResponse {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Value>::deserialize(deserializer).map(Self::from_values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn keeps_rejected_records_with_their_reason() {
+        let response: StationsResponse = serde_json::from_value(json!([
+            {
+                "idstazione": "-/1005292,4503435/simnpr", "ordinamento": 9999, "nomestaz": "Ongina Po",
+                "lon": "1005292", "lat": "4503435", "value": 1.5,
+                "soglia1": 1, "soglia2": 2, "soglia3": 3
+            },
+            {"idstazione": "-/1,2/simnpr", "nomestaz": 42},
+            "not a station"
+        ]))
+        .unwrap();
+
+        assert_eq!(response.stations().len(), 1);
+        assert_eq!(
+            response.stations().iter().next().unwrap().nomestaz(),
+            "Ongina Po"
+        );
+        assert_eq!(response.rejected().len(), 2);
+        assert_eq!(response.rejected()[1].raw(), &json!("not a station"));
+        assert!(!response.rejected()[0].error().is_empty());
+    }
+
+    #[test]
+    fn decodes_the_sample_snapshot_without_rejections() {
+        let response: StationsResponse =
+            serde_json::from_str(include_str!("../../../../stations.json")).unwrap();
+
+        assert_eq!(response.stations().len(), 247);
+        assert!(response.rejected().is_empty());
+    }
+
+    #[test]
+    fn variable_is_applied_to_the_stations() {
+        let response =
+            StationsResponse::from_values(Vec::new()).with_variable(Variable::HYDROMETRIC_LEVEL);
+        let (stations, rejected) = response.into_parts();
+
+        assert!(stations.is_empty() && rejected.is_empty());
+        assert_eq!(stations.variable(), &Variable::HYDROMETRIC_LEVEL);
+    }
+}
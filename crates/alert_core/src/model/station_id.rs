@@ -0,0 +1,138 @@
+use std::{fmt, hash::Hash, str::FromStr};
+
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+use crate::{api::StationsError, model::Coordinates};
+
+/// Upstream station identifier, e.g. `-/1133462,4452053/simnbo`.
+///
+/// The middle segment holds the station coordinates in the same fixed-point format as the
+/// `lon`/`lat` fields, the last one is the network code (`simnbo`, `simnpr`, `spdsra`, ...).
+#[derive(Debug, Clone, SerializeDisplay, DeserializeFromStr)]
+pub struct StationId {
+    raw: String,
+    network_start: usize,
+    coordinates: Coordinates,
+}
+
+impl StationId {
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn network(&self) -> &str {
+        &self.raw[self.network_start..]
+    }
+
+    pub fn coordinates(&self) -> Coordinates {
+        self.coordinates
+    }
+}
+
+impl FromStr for StationId {
+    type Err = StationsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || StationsError::InvalidStationId(s.to_owned());
+
+        let mut parts = s.split('/');
+        let (Some(prefix), Some(position), Some(network), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+
+        if prefix.is_empty()
+            || network.is_empty()
+            || !network.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(invalid());
+        }
+
+        let (lon, lat) = position.split_once(',').ok_or_else(invalid)?;
+        let coordinates = Coordinates::from_upstream(lon, lat)?;
+
+        Ok(Self {
+            raw: s.to_owned(),
+            network_start: s.len() - network.len(),
+            coordinates,
+        })
+    }
+}
+
+impl fmt::Display for StationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl AsRef<str> for StationId {
+    fn as_ref(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl PartialEq for StationId {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl Eq for StationId {}
+
+impl Hash for StationId {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.raw.hash(state);
+    }
+}
+
+impl PartialOrd for StationId {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for StationId {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.raw.cmp(&other.raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_coordinates_and_network() {
+        let id: StationId = "-/1129579,4472121/simnbo".parse().unwrap();
+        assert_eq!(id.network(), "simnbo");
+        assert_eq!(id.coordinates().longitude(), 11.29579);
+        assert_eq!(id.coordinates().latitude(), 44.72121);
+        assert_eq!(id.to_string(), "-/1129579,4472121/simnbo");
+    }
+
+    #[test]
+    fn rejects_malformed_ids() {
+        for raw in [
+            "",
+            "-/1129579,4472121",
+            "-/1129579,4472121/simnbo/extra",
+            "/1129579,4472121/simnbo",
+            "-/1129579,4472121/",
+            "-/1129579,4472121/sim-nbo",
+            "-/1129579/simnbo",
+        ] {
+            assert!(raw.parse::<StationId>().is_err(), "{raw:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn serializes_as_the_raw_string() {
+        let id: StationId = serde_json::from_str("\"-/1129579,4472121/simnbo\"").unwrap();
+        assert_eq!(
+            serde_json::to_string(&id).unwrap(),
+            "\"-/1129579,4472121/simnbo\""
+        );
+        assert!(serde_json::from_str::<StationId>("\"nope\"").is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::StationsError,
+    model::{AlertLevel, Coordinates, StationId, Thresholds},
+};
+
+/// Static metadata of a station: everything upstream sends for it but the reading itself.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct StationInfo {
+    idstazione: StationId,
+    ordinamento: usize,
+    nomestaz: String,
+    lon: String,
+    lat: String,
+    #[serde(flatten)]
+    thresholds: Thresholds,
+}
+
+impl StationInfo {
+    pub fn new(
+        idstazione: StationId,
+        ordinamento: usize,
+        nomestaz: impl Into<String>,
+        lon: impl Into<String>,
+        lat: impl Into<String>,
+        thresholds: Thresholds,
+    ) -> Self {
+        Self {
+            idstazione,
+            ordinamento,
+            nomestaz: nomestaz.into(),
+            lon: lon.into(),
+            lat: lat.into(),
+            thresholds,
+        }
+    }
+
+    pub fn idstazione(&self) -> &StationId {
+        &self.idstazione
+    }
+
+    /// Display order suggested by upstream, `9999` for most stations.
+    pub fn ordinamento(&self) -> usize {
+        self.ordinamento
+    }
+
+    pub fn nomestaz(&self) -> &str {
+        &self.nomestaz
+    }
+
+    /// Longitude as sent by upstream, see [`Coordinates::from_upstream`].
+    pub fn lon(&self) -> &str {
+        &self.lon
+    }
+
+    /// Latitude as sent by upstream, see [`Coordinates::from_upstream`].
+    pub fn lat(&self) -> &str {
+        &self.lat
+    }
+
+    pub fn coordinates(&self) -> Result<Coordinates, StationsError> {
+        Coordinates::from_upstream(&self.lon, &self.lat)
+    }
+
+    pub fn thresholds(&self) -> &Thresholds {
+        &self.thresholds
+    }
+
+    /// Alert level of a reading of this station.
+    pub fn alert_level(&self, value: Option<f32>) -> AlertLevel {
+        match value {
+            Some(value) => self.thresholds.level_for(value),
+            None => AlertLevel::NoData,
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::model::AlertLevel;
+
+/// Alert thresholds of a station.
+///
+/// Upstream reports a threshold that was never configured as `0`, which is why zero is read as
+/// "not defined" and written back as `0` when serializing.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub struct Thresholds {
+    #[serde(deserialize_with = "de_threshold", serialize_with = "ser_threshold")]
+    soglia1: Option<f32>,
+    #[serde(deserialize_with = "de_threshold", serialize_with = "ser_threshold")]
+    soglia2: Option<f32>,
+    #[serde(deserialize_with = "de_threshold", serialize_with = "ser_threshold")]
+    soglia3: Option<f32>,
+}
+
+impl Thresholds {
+    pub fn new(soglia1: Option<f32>, soglia2: Option<f32>, soglia3: Option<f32>) -> Self {
+        Self {
+            soglia1: soglia1.filter(is_defined),
+            soglia2: soglia2.filter(is_defined),
+            soglia3: soglia3.filter(is_defined),
+        }
+    }
+
+    pub fn soglia1(&self) -> Option<f32> {
+        self.soglia1
+    }
+
+    pub fn soglia2(&self) -> Option<f32> {
+        self.soglia2
+    }
+
+    pub fn soglia3(&self) -> Option<f32> {
+        self.soglia3
+    }
+
+    /// Returns `true` when none of the three thresholds is configured.
+    pub fn is_unknown(&self) -> bool {
+        self.soglia1.is_none() && self.soglia2.is_none() && self.soglia3.is_none()
+    }
+
+    /// Defined thresholds paired with the level reached when a reading exceeds them.
+    pub fn levels(&self) -> impl Iterator<Item = (AlertLevel, f32)> {
+        [
+            (AlertLevel::Level1, self.soglia1),
+            (AlertLevel::Level2, self.soglia2),
+            (AlertLevel::Level3, self.soglia3),
+        ]
+        .into_iter()
+        .filter_map(|(level, threshold)| threshold.map(|threshold| (level, threshold)))
+    }
+
+    pub fn level_for(&self, value: f32) -> AlertLevel {
+        if self.is_unknown() {
+            return AlertLevel::NoThresholds;
+        }
+
+        self.levels()
+            .filter(|(_, threshold)| value > *threshold)
+            .map(|(level, _)| level)
+            .max()
+            .unwrap_or(AlertLevel::Normal)
+    }
+}
+
+fn is_defined(threshold: &f32) -> bool {
+    *threshold != 0.0 && threshold.is_finite()
+}
+
+fn de_threshold<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f32>, D::Error> {
+    Ok(Option::<f32>::deserialize(deserializer)?.filter(is_defined))
+}
+
+fn ser_threshold<S: Serializer>(threshold: &Option<f32>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f32(threshold.unwrap_or(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_means_not_configured() {
+        let thresholds: Thresholds =
+            serde_json::from_str(r#"{"soglia1": 2.5, "soglia2": 0, "soglia3": 0.0}"#).unwrap();
+        assert_eq!(thresholds, Thresholds::new(Some(2.5), None, None));
+        assert_eq!(
+            Thresholds::new(Some(0.0), Some(f32::NAN), None),
+            Thresholds::default()
+        );
+
+        let json = serde_json::to_value(thresholds).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"soglia1": 2.5, "soglia2": 0.0, "soglia3": 0.0})
+        );
+    }
+
+    #[test]
+    fn level_is_the_highest_exceeded_threshold() {
+        let thresholds = Thresholds::new(Some(1.0), Some(2.0), Some(3.0));
+        assert_eq!(thresholds.level_for(1.0), AlertLevel::Normal);
+        assert_eq!(thresholds.level_for(1.5), AlertLevel::Level1);
+        assert_eq!(thresholds.level_for(2.5), AlertLevel::Level2);
+        assert_eq!(thresholds.level_for(9.0), AlertLevel::Level3);
+    }
+
+    #[test]
+    fn missing_thresholds_are_skipped() {
+        let thresholds = Thresholds::new(None, Some(2.0), None);
+        assert_eq!(thresholds.level_for(1.5), AlertLevel::Normal);
+        assert_eq!(thresholds.level_for(2.5), AlertLevel::Level2);
+        assert_eq!(
+            Thresholds::default().level_for(100.0),
+            AlertLevel::NoThresholds
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, de::DeserializeOwned};
+
+use crate::{
+    api::{StationsError, Variable},
+    model::{StationId, Stations, StationsResponse, TimeSeries, TimeValue},
+    source::{BoxFuture, StationSource, run_blocking},
+    storage::{SnapshotFile, slug},
+};
+
+const STATIONS_FILE: &str = "stations.json";
+
+/// A snapshot file, as saved from upstream or as written by a `JsonDirStore`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredStations {
+    Stored(SnapshotFile<StationsResponse>),
+    Upstream(StationsResponse),
+}
+
+/// Reads upstream payloads saved as JSON files in a directory.
+///
+/// Snapshots are looked up as `<variable>/stations_<unix millis>.json`, the layout written by
+/// [`JsonDirStore`](crate::storage::JsonDirStore), then as `stations_<unix millis>.json` and
+/// finally as `stations.json`.
+/// Time series are looked up as `timeseries_<station id>.json` and then as
+/// `timeseries_<station name>.json`, both lowercased with every non alphanumeric run replaced
+/// by `_`: the series of `-/1129579,4472121/simnbo` (Cento) can be stored as
+/// `timeseries_1129579_4472121_simnbo.json` or `timeseries_cento.json`.
+///
+/// Snapshots written by [`JsonDirStore`](crate::storage::JsonDirStore) keep the variable they
+/// were fetched for; plain upstream payloads don't record it and are tagged with the requested
+/// one.
+#[derive(Clone, Debug)]
+pub struct FixtureSource {
+    dir: PathBuf,
+}
+
+impl FixtureSource {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn load_stations(
+        &self,
+        variable: &Variable,
+        time: DateTime<Utc>,
+    ) -> Result<StationsResponse, StationsError> {
+        let file = format!("stations_{}.json", time.timestamp_millis());
+        let candidates = [
+            format!("{}/{file}", slug(variable.code())),
+            file,
+            STATIONS_FILE.to_owned(),
+        ];
+        let (mut response, variable) = match self.read_first(&candidates)? {
+            StoredStations::Stored(SnapshotFile { variable, stations }) => (stations, variable),
+            StoredStations::Upstream(stations) => (stations, variable.clone()),
+        };
+        response.stations_mut().sort_by_alert_desc();
+        Ok(response.with_variable(variable))
+    }
+
+    fn load_timeseries(
+        &self,
+        variable: &Variable,
+        station_id: &StationId,
+    ) -> Result<TimeSeries, StationsError> {
+        let mut candidates = vec![format!("timeseries_{}.json", slug(station_id.as_str()))];
+        if let Some(name) = self.station_name(station_id) {
+            candidates.push(format!("timeseries_{}.json", slug(&name)));
+        }
+
+        let series: Vec<TimeValue> = self.read_first(&candidates)?;
+        Ok(TimeSeries::new(series).with_variable(variable.clone()))
+    }
+
+    fn station_name(&self, station_id: &StationId) -> Option<String> {
+        let stations: Stations = self.read(&self.dir.join(STATIONS_FILE)).ok()?;
+        stations
+            .iter()
+            .find(|station| station.idstazione() == station_id)
+            .map(|station| station.nomestaz().to_owned())
+    }
+
+    fn read_first<T: DeserializeOwned>(&self, candidates: &[String]) -> Result<T, StationsError> {
+        let path = candidates
+            .iter()
+            .map(|name| self.dir.join(name))
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| self.dir.join(&candidates[0]));
+        self.read(&path)
+    }
+
+    fn read<T: DeserializeOwned>(&self, path: &Path) -> Result<T, StationsError> {
+        let body = std::fs::read_to_string(path).map_err(|source| StationsError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        serde_json::from_str(&body).map_err(|error| StationsError::decode(&body, error))
+    }
+}
+
+impl StationSource for FixtureSource {
+    fn stations_at<'a>(
+        &'a self,
+        variable: &'a Variable,
+        time: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<StationsResponse, StationsError>> {
+        let source = self.clone();
+        let variable = variable.clone();
+        Box::pin(run_blocking(move || source.load_stations(&variable, time)))
+    }
+
+    fn station_timeseries<'a>(
+        &'a self,
+        variable: &'a Variable,
+        station_id: &'a StationId,
+    ) -> BoxFuture<'a, Result<TimeSeries, StationsError>> {
+        let source = self.clone();
+        let variable = variable.clone();
+        let station_id = station_id.clone();
+        Box::pin(run_blocking(move || {
+            source.load_timeseries(&variable, &station_id)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{JsonDirStore, SnapshotStore as _};
+
+    /// The sample payloads at the root of the repository.
+    fn samples() -> FixtureSource {
+        FixtureSource::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_default_snapshot() {
+        let variable = Variable::HYDROMETRIC_LEVEL;
+        let response = samples()
+            .stations_at(&variable, DateTime::UNIX_EPOCH)
+            .await
+            .unwrap();
+
+        assert_eq!(response.stations().len(), 247);
+        assert_eq!(response.stations().variable(), &variable);
+        let levels = response.stations().iter().map(|s| s.alert_level());
+        assert!(levels.is_sorted_by(|a, b| a >= b));
+    }
+
+    #[tokio::test]
+    async fn finds_time_series_by_station_name() {
+        let cento = "-/1129579,4472121/simnbo".parse::<StationId>().unwrap();
+        let series = samples()
+            .station_timeseries(&Variable::HYDROMETRIC_LEVEL, &cento)
+            .await
+            .unwrap();
+
+        assert!(!series.is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_files_are_io_errors() {
+        let unknown = "-/1,2/nowhere".parse::<StationId>().unwrap();
+        let Err(error) = FixtureSource::new("/nonexistent")
+            .station_timeseries(&Variable::HYDROMETRIC_LEVEL, &unknown)
+            .await
+        else {
+            panic!("expected no time series");
+        };
+
+        assert!(
+            matches!(error, StationsError::Io { path, .. } if path.ends_with("timeseries_1_2_nowhere.json"))
+        );
+    }
+
+    #[tokio::test]
+    async fn stored_snapshots_are_read_per_variable() {
+        let dir = std::env::temp_dir().join(format!("alert_core_fixture_{}", std::process::id()));
+        let time = DateTime::from_timestamp_millis(1_700_000_100_000).unwrap();
+        let stored = Variable::KNOWN[1].clone();
+        let stations = samples()
+            .load_stations(&stored, time)
+            .unwrap()
+            .into_stations();
+        JsonDirStore::new(&dir)
+            .unwrap()
+            .store(time, &stations)
+            .unwrap();
+
+        let source = FixtureSource::new(&dir);
+        let response = source.stations_at(&stored, time).await.unwrap();
+        let other = source.stations_at(&Variable::HYDROMETRIC_LEVEL, time).await;
+
+        assert_eq!(response.stations().variable(), &stored);
+        assert_eq!(response.stations().len(), 247);
+        assert!(matches!(other, Err(StationsError::Io { .. })));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
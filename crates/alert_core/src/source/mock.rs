@@ -0,0 +1,169 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    api::{StationsError, Variable},
+    model::{StationId, StationsResponse, TimeSeries},
+    source::{BoxFuture, StationSource},
+};
+
+/// Request received by a [`MockSource`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockCall {
+    StationsAt {
+        variable: Variable,
+        time: DateTime<Utc>,
+    },
+    StationTimeseries {
+        variable: Variable,
+        station_id: StationId,
+    },
+}
+
+/// In-memory source answering with scripted results, in the order they were pushed.
+///
+/// Clones share the same script and call log. Once a script runs out every further call fails
+/// with [`StationsError::Unknown`].
+#[derive(Clone, Default)]
+pub struct MockSource {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    stations: VecDeque<Result<StationsResponse, StationsError>>,
+    timeseries: VecDeque<Result<TimeSeries, StationsError>>,
+    calls: Vec<MockCall>,
+}
+
+impl MockSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_stations(&self, result: Result<StationsResponse, StationsError>) -> &Self {
+        self.lock().stations.push_back(result);
+        self
+    }
+
+    pub fn push_timeseries(&self, result: Result<TimeSeries, StationsError>) -> &Self {
+        self.lock().timeseries.push_back(result);
+        self
+    }
+
+    /// Requests received so far, oldest first.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.lock().calls.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl StationSource for MockSource {
+    fn stations_at<'a>(
+        &'a self,
+        variable: &'a Variable,
+        time: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<StationsResponse, StationsError>> {
+        let mut state = self.lock();
+        state.calls.push(MockCall::StationsAt {
+            variable: variable.clone(),
+            time,
+        });
+        let result = state.stations.pop_front().unwrap_or_else(|| {
+            Err(StationsError::Unknown(
+                "mock stations script exhausted".to_owned(),
+            ))
+        });
+        let result = result.map(|response| response.with_variable(variable.clone()));
+        Box::pin(async move { result })
+    }
+
+    fn station_timeseries<'a>(
+        &'a self,
+        variable: &'a Variable,
+        station_id: &'a StationId,
+    ) -> BoxFuture<'a, Result<TimeSeries, StationsError>> {
+        let mut state = self.lock();
+        state.calls.push(MockCall::StationTimeseries {
+            variable: variable.clone(),
+            station_id: station_id.clone(),
+        });
+        let result = state.timeseries.pop_front().unwrap_or_else(|| {
+            Err(StationsError::Unknown(
+                "mock timeseries script exhausted".to_owned(),
+            ))
+        });
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Stations;
+
+    #[tokio::test]
+    async fn replays_the_script_and_records_calls() {
+        let mock = MockSource::new();
+        mock.push_stations(Ok(StationsResponse::new(
+            Stations::new(Vec::new()),
+            Vec::new(),
+        )))
+        .push_stations(Err(StationsError::InvalidTimestamp("x".into())));
+        let clone = mock.clone();
+        let variable = Variable::HYDROMETRIC_LEVEL;
+
+        let first = clone.stations_at(&variable, DateTime::UNIX_EPOCH).await;
+        let second = mock.stations_at(&variable, DateTime::UNIX_EPOCH).await;
+        let third = mock.stations_at(&variable, DateTime::UNIX_EPOCH).await;
+
+        assert_eq!(first.unwrap().stations().variable(), &variable);
+        assert!(matches!(second, Err(StationsError::InvalidTimestamp(_))));
+        assert!(matches!(third, Err(StationsError::Unknown(_))));
+        assert_eq!(mock.calls().len(), 3);
+        assert_eq!(
+            mock.calls()[0],
+            MockCall::StationsAt {
+                variable,
+                time: DateTime::UNIX_EPOCH
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn time_series_have_their_own_script() {
+        let mock = MockSource::new();
+        mock.push_timeseries(Ok(TimeSeries::new(Vec::new())));
+        let station_id = "-/1,2/simnbo".parse::<StationId>().unwrap();
+        let variable = Variable::HYDROMETRIC_LEVEL;
+
+        assert!(
+            mock.station_timeseries(&variable, &station_id)
+                .await
+                .is_ok()
+        );
+        assert!(
+            mock.station_timeseries(&variable, &station_id)
+                .await
+                .is_err()
+        );
+        assert!(
+            mock.stations_at(&variable, DateTime::UNIX_EPOCH)
+                .await
+                .is_err()
+        );
+        assert!(matches!(
+            &mock.calls()[0],
+            MockCall::StationTimeseries { station_id: id, .. } if id == &station_id
+        ));
+    }
+}
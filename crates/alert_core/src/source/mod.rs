@@ -0,0 +1,66 @@
+mod fixture;
+mod mock;
+
+use std::{future::Future, pin::Pin};
+
+use chrono::{DateTime, Utc};
+
+pub use crate::source::{
+    fixture::FixtureSource,
+    mock::{MockCall, MockSource},
+};
+use crate::{
+    api::{AlertClient, StationsError, Variable},
+    model::{StationId, StationsResponse, TimeSeries},
+};
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Where station snapshots and time series come from.
+///
+/// Object safe, so that consumers can hold an `Arc<dyn StationSource>` and switch between the
+/// live [`AlertClient`], a [`FixtureSource`] directory and a scripted [`MockSource`].
+pub trait StationSource: Send + Sync {
+    fn stations_at<'a>(
+        &'a self,
+        variable: &'a Variable,
+        time: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<StationsResponse, StationsError>>;
+
+    fn station_timeseries<'a>(
+        &'a self,
+        variable: &'a Variable,
+        station_id: &'a StationId,
+    ) -> BoxFuture<'a, Result<TimeSeries, StationsError>>;
+}
+
+impl StationSource for AlertClient {
+    fn stations_at<'a>(
+        &'a self,
+        variable: &'a Variable,
+        time: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<StationsResponse, StationsError>> {
+        Box::pin(self.stations_response_at(variable, time))
+    }
+
+    fn station_timeseries<'a>(
+        &'a self,
+        variable: &'a Variable,
+        station_id: &'a StationId,
+    ) -> BoxFuture<'a, Result<TimeSeries, StationsError>> {
+        Box::pin(self.variable_timeseries(variable, station_id))
+    }
+}
+
+/// Runs blocking I/O on the blocking thread pool, so that sources reading files don't stall the
+/// runtime. A panic in `operation` is resumed in the caller.
+pub(crate) async fn run_blocking<T, F>(operation: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(operation).await {
+        Ok(result) => result,
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    }
+}
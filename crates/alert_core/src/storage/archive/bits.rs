@@ -0,0 +1,114 @@
+/// Packs values into bytes, most significant bit first.
+#[derive(Debug, Default)]
+pub(super) struct BitWriter {
+    bytes: Vec<u8>,
+    /// Bits still free in the last byte.
+    free: u32,
+}
+
+impl BitWriter {
+    pub(super) fn bit(&mut self, bit: bool) {
+        self.bits(u64::from(bit), 1);
+    }
+
+    /// Writes the `count` low bits of `value`.
+    pub(super) fn bits(&mut self, value: u64, mut count: u32) {
+        debug_assert!(count <= 64);
+        while count > 0 {
+            if self.free == 0 {
+                self.bytes.push(0);
+                self.free = 8;
+            }
+            let taken = count.min(self.free);
+            let chunk = (value >> (count - taken)) & ((1 << taken) - 1);
+            *self.bytes.last_mut().expect("pushed above") |= (chunk << (self.free - taken)) as u8;
+            self.free -= taken;
+            count -= taken;
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub(super) fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads back what a [`BitWriter`] wrote; `None` past the end.
+pub(super) struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(super) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub(super) fn bit(&mut self) -> Option<bool> {
+        self.bits(1).map(|bit| bit == 1)
+    }
+
+    pub(super) fn bits(&mut self, count: u32) -> Option<u64> {
+        debug_assert!(count <= 64);
+        if self.position + count as usize > self.bytes.len() * 8 {
+            return None;
+        }
+        let mut value = 0;
+        let mut count = count;
+        while count > 0 {
+            let byte = self.bytes[self.position / 8];
+            let offset = (self.position % 8) as u32;
+            let taken = count.min(8 - offset);
+            let chunk = (u64::from(byte) >> (8 - offset - taken)) & ((1 << taken) - 1);
+            value = (value << taken) | chunk;
+            self.position += taken as usize;
+            count -= taken;
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_was_written() {
+        let values = [
+            (1, 1),
+            (0, 0),
+            (0b101, 3),
+            (u64::MAX, 64),
+            (0x1234, 13),
+            (0, 7),
+            (u64::from(u32::MAX), 33),
+        ];
+        let mut writer = BitWriter::default();
+        for (value, count) in values {
+            writer.bits(value, count);
+        }
+        writer.bit(true);
+        assert_eq!(writer.len(), 16);
+
+        let bytes = writer.into_bytes();
+        let mut reader = BitReader::new(&bytes);
+        for (value, count) in values {
+            assert_eq!(reader.bits(count), Some(value));
+        }
+        assert_eq!(reader.bit(), Some(true));
+        assert_eq!(reader.bits(6), Some(0), "padding of the last byte");
+        assert_eq!(reader.bit(), None);
+    }
+
+    #[test]
+    fn stops_at_the_end() {
+        let mut reader = BitReader::new(&[0b1010_0000]);
+        assert_eq!(reader.bits(9), None);
+        assert_eq!(reader.bits(3), Some(0b101));
+        assert_eq!(reader.bits(6), None);
+        assert_eq!(reader.bits(5), Some(0));
+    }
+}
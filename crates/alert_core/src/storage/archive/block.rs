@@ -0,0 +1,364 @@
+use crate::storage::{
+    StorageError,
+    archive::bits::{BitReader, BitWriter},
+};
+
+/// Bits of the missing value marker, missing readings are stored as this NaN.
+const MISSING: u32 = f32::NAN.to_bits();
+
+/// Delta-of-delta buckets after the `0` prefix of an unchanged delta: each bucket adds a `1` to
+/// the prefix and holds a zigzag encoded delta-of-delta of that many bits. The last bucket ends
+/// the prefix without a `0` and fits any delta-of-delta between two `u32` timestamps.
+const DOD_BUCKETS: [u32; 4] = [7, 9, 12, 33];
+/// Fewest bits of a reading after the first: an unchanged delta and an unchanged value.
+const MIN_READING_BITS: u64 = 2;
+/// Most bits of a reading after the first: a delta-of-delta in the last bucket and a value
+/// XOR with its own window.
+const MAX_READING_BITS: u64 = 4 + 33 + 2 + 5 + 5 + 32;
+
+/// Gorilla encoder of the readings of one station: delta-of-delta timestamps and XOR values.
+pub(super) struct BlockEncoder {
+    first_time: u32,
+    first_value: u32,
+    count: u32,
+    last_time: u32,
+    last_delta: i64,
+    last_value: u32,
+    /// Leading and trailing zeros of the last XOR written with its own window.
+    window: Option<(u32, u32)>,
+    bits: BitWriter,
+}
+
+impl BlockEncoder {
+    pub(super) fn new(time: u32, value: Option<f32>) -> Self {
+        let value = to_bits(value);
+        Self {
+            first_time: time,
+            first_value: value,
+            count: 1,
+            last_time: time,
+            last_delta: 0,
+            last_value: value,
+            window: None,
+            bits: BitWriter::default(),
+        }
+    }
+
+    /// Appends a reading, `time` has to be after the last one.
+    pub(super) fn push(&mut self, time: u32, value: Option<f32>) {
+        debug_assert!(time > self.last_time);
+        let delta = i64::from(time - self.last_time);
+        self.write_dod(delta - self.last_delta);
+        self.last_time = time;
+        self.last_delta = delta;
+
+        let value = to_bits(value);
+        self.write_xor(value ^ self.last_value);
+        self.last_value = value;
+        self.count += 1;
+    }
+
+    fn write_dod(&mut self, dod: i64) {
+        let zigzag = ((dod << 1) ^ (dod >> 63)) as u64;
+        if zigzag == 0 {
+            self.bits.bit(false);
+            return;
+        }
+        for (i, width) in DOD_BUCKETS.into_iter().enumerate() {
+            let last = i == DOD_BUCKETS.len() - 1;
+            if last || zigzag < 1 << width {
+                self.bits.bit(true);
+                if !last {
+                    self.bits.bit(false);
+                }
+                self.bits.bits(zigzag, width);
+                return;
+            }
+            self.bits.bit(true);
+        }
+    }
+
+    fn write_xor(&mut self, xor: u32) {
+        if xor == 0 {
+            self.bits.bit(false);
+            return;
+        }
+        self.bits.bit(true);
+
+        let (leading, trailing) = (xor.leading_zeros(), xor.trailing_zeros());
+        match self.window {
+            Some((window_leading, window_trailing))
+                if leading >= window_leading && trailing >= window_trailing =>
+            {
+                self.bits.bit(false);
+                self.bits.bits(
+                    u64::from(xor >> window_trailing),
+                    32 - window_leading - window_trailing,
+                );
+            }
+            _ => {
+                let meaningful = 32 - leading - trailing;
+                self.bits.bit(true);
+                self.bits.bits(u64::from(leading), 5);
+                self.bits.bits(u64::from(meaningful - 1), 5);
+                self.bits.bits(u64::from(xor >> trailing), meaningful);
+                self.window = Some((leading, trailing));
+            }
+        }
+    }
+
+    pub(super) fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub(super) fn last_time(&self) -> u32 {
+        self.last_time
+    }
+
+    /// Size of the payload so far, in bytes.
+    pub(super) fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub(super) fn finish(self) -> EncodedBlock {
+        EncodedBlock {
+            count: self.count,
+            first_time: self.first_time,
+            last_time: self.last_time,
+            first_value: self.first_value,
+            payload: self.bits.into_bytes(),
+        }
+    }
+}
+
+/// A block as laid out in the archive, after the station id.
+pub(super) struct EncodedBlock {
+    pub(super) count: u32,
+    pub(super) first_time: u32,
+    pub(super) last_time: u32,
+    pub(super) first_value: u32,
+    pub(super) payload: Vec<u8>,
+}
+
+/// Rejects a count of readings and a payload length that can't belong to the same block, before
+/// anything is allocated for them.
+pub(super) fn check_sizes(count: u32, payload_len: u32) -> Result<(), StorageError> {
+    let Some(following) = count.checked_sub(1).map(u64::from) else {
+        return Err(StorageError::Corrupt("empty block".to_owned()));
+    };
+    let payload_bits = u64::from(payload_len) * 8;
+    if following * MIN_READING_BITS > payload_bits
+        || payload_bits > (following * MAX_READING_BITS).div_ceil(8) * 8
+    {
+        return Err(StorageError::Corrupt(format!(
+            "{count} readings don't fit in {payload_len} bytes"
+        )));
+    }
+    Ok(())
+}
+
+impl EncodedBlock {
+    /// Decodes the `(time, value)` pairs of the block.
+    pub(super) fn decode(&self) -> Result<Vec<(u32, Option<f32>)>, StorageError> {
+        let truncated = || StorageError::Corrupt("truncated block".to_owned());
+        let payload_len = u32::try_from(self.payload.len())
+            .map_err(|_| StorageError::Corrupt("block too large".to_owned()))?;
+        check_sizes(self.count, payload_len)?;
+
+        let mut readings = Vec::with_capacity(self.count as usize);
+        readings.push((self.first_time, from_bits(self.first_value)));
+        let mut bits = BitReader::new(&self.payload);
+        let (mut time, mut delta, mut value) = (i64::from(self.first_time), 0, self.first_value);
+        let mut window = (0, 0);
+
+        for _ in 1..self.count {
+            delta += read_dod(&mut bits).ok_or_else(truncated)?;
+            time += delta;
+            let time = u32::try_from(time)
+                .ok()
+                .filter(|&time| delta > 0 && time <= self.last_time)
+                .ok_or_else(|| StorageError::Corrupt(format!("invalid timestamp {time}")))?;
+
+            if bits.bit().ok_or_else(truncated)? {
+                if bits.bit().ok_or_else(truncated)? {
+                    let leading = bits.bits(5).ok_or_else(truncated)? as u32;
+                    let meaningful = bits.bits(5).ok_or_else(truncated)? as u32 + 1;
+                    let trailing = 32u32
+                        .checked_sub(leading + meaningful)
+                        .ok_or_else(|| StorageError::Corrupt("invalid XOR window".to_owned()))?;
+                    window = (leading, trailing);
+                }
+                let (leading, trailing) = window;
+                let xor = bits.bits(32 - leading - trailing).ok_or_else(truncated)? as u32;
+                value ^= xor << trailing;
+            }
+            readings.push((time, from_bits(value)));
+        }
+
+        if readings.last().map(|&(time, _)| time) != Some(self.last_time) {
+            return Err(StorageError::Corrupt(
+                "block doesn't end at its last time".to_owned(),
+            ));
+        }
+        Ok(readings)
+    }
+}
+
+fn read_dod(bits: &mut BitReader<'_>) -> Option<i64> {
+    if !bits.bit()? {
+        return Some(0);
+    }
+    for (i, width) in DOD_BUCKETS.into_iter().enumerate() {
+        let last = i == DOD_BUCKETS.len() - 1;
+        if last || !bits.bit()? {
+            let zigzag = bits.bits(width)?;
+            return Some((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64));
+        }
+    }
+    unreachable!("the last bucket always returns")
+}
+
+fn to_bits(value: Option<f32>) -> u32 {
+    value.map_or(MISSING, f32::to_bits)
+}
+
+fn from_bits(bits: u32) -> Option<f32> {
+    Some(f32::from_bits(bits)).filter(|value| !value.is_nan())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(readings: &[(u32, Option<f32>)]) -> BlockEncoder {
+        let (&(time, value), rest) = readings.split_first().unwrap();
+        let mut encoder = BlockEncoder::new(time, value);
+        for &(time, value) in rest {
+            encoder.push(time, value);
+        }
+        encoder
+    }
+
+    fn round_trip(readings: &[(u32, Option<f32>)]) -> Vec<(u32, Option<f32>)> {
+        encode(readings).finish().decode().unwrap()
+    }
+
+    #[test]
+    fn delta_of_deltas_at_every_bucket_boundary() {
+        const DELTA: i64 = 1 << 20;
+        let mut dods = vec![0, 1, -1];
+        for width in DOD_BUCKETS {
+            // Largest and smallest delta-of-delta fitting the bucket, and one past them.
+            let max = (1_i64 << (width - 1)) - 1;
+            dods.extend([max, max + 1, -max - 1, -max - 2]);
+        }
+
+        let mut times = vec![1_000_000_000_u32];
+        for dod in dods.into_iter().filter(|dod| dod.abs() < 1 << 28) {
+            // Each delta-of-delta followed by its opposite, back to the regular delta.
+            let last = i64::from(*times.last().unwrap());
+            times.push(u32::try_from(last + DELTA + dod).unwrap());
+            times.push(u32::try_from(last + 2 * DELTA + dod).unwrap());
+        }
+        let readings = times
+            .into_iter()
+            .map(|time| (time, Some(1.0)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(round_trip(&readings), readings);
+    }
+
+    #[test]
+    fn delta_of_deltas_across_the_whole_range() {
+        for times in [
+            [0, 1, u32::MAX],
+            [0, u32::MAX - 1, u32::MAX],
+            [u32::MAX - 2, u32::MAX - 1, u32::MAX],
+        ] {
+            let readings = times.map(|time| (time, None));
+            assert_eq!(round_trip(&readings), readings);
+        }
+    }
+
+    #[test]
+    fn reuses_the_xor_window() {
+        let mut encoder = encode(&[(0, Some(1.0)), (1, Some(1.5))]);
+        let window = encoder.window;
+        assert_eq!(window, Some((9, 22)));
+
+        encoder.push(2, Some(1.0));
+        assert_eq!(encoder.window, window, "same XOR, same window");
+        encoder.push(3, Some(1.25));
+        assert_eq!(encoder.window, Some((10, 21)), "wider XOR, new window");
+        encoder.push(4, Some(1.0));
+        encoder.push(5, Some(1.0));
+
+        let values = encoder
+            .finish()
+            .decode()
+            .unwrap()
+            .into_iter()
+            .map(|(_, value)| value.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values, [1.0, 1.5, 1.0, 1.25, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn special_values() {
+        let readings = [
+            (0, Some(0.0)),
+            (1, Some(-0.0)),
+            (2, None),
+            (3, Some(f32::NAN)),
+            (4, Some(f32::INFINITY)),
+            (5, Some(f32::MIN_POSITIVE)),
+            (6, None),
+            (7, Some(-1.5)),
+        ];
+
+        let decoded = round_trip(&readings);
+
+        let bits = |readings: &[(u32, Option<f32>)]| {
+            readings
+                .iter()
+                .map(|(time, value)| (*time, value.map(f32::to_bits)))
+                .collect::<Vec<_>>()
+        };
+        let mut expected = bits(&readings);
+        expected[3].1 = None;
+        assert_eq!(
+            bits(&decoded),
+            expected,
+            "NaN is missing, zeros keep their sign"
+        );
+    }
+
+    #[test]
+    fn rejects_sizes_that_cant_match() {
+        assert!(check_sizes(1, 0).is_ok());
+        assert!(check_sizes(2, 1).is_ok());
+        assert!(check_sizes(0, 0).is_err());
+        assert!(check_sizes(1, 1).is_err());
+        assert!(check_sizes(u32::MAX, 16).is_err());
+        assert!(check_sizes(2, 11).is_ok());
+        assert!(check_sizes(2, 12).is_err());
+    }
+
+    #[test]
+    fn rejects_corrupt_blocks() {
+        let readings = [(10, Some(1.0)), (20, Some(2.0)), (35, None)];
+        let block = encode(&readings).finish();
+
+        let mut truncated = encode(&readings).finish();
+        truncated.payload.pop();
+        let mut wrong_end = encode(&readings).finish();
+        wrong_end.last_time = 30;
+        let mut more = encode(&readings).finish();
+        more.count = 5;
+
+        assert!(block.decode().is_ok());
+        for corrupt in [truncated, wrong_end, more] {
+            assert!(matches!(corrupt.decode(), Err(StorageError::Corrupt(_))));
+        }
+    }
+}
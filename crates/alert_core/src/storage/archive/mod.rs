@@ -0,0 +1,413 @@
+//! Compact binary archive of readings.
+//!
+//! Readings are grouped in per-station blocks of up to [`DEFAULT_BLOCK_SIZE`] readings, encoded
+//! like Gorilla does: timestamps are whole seconds stored as delta-of-delta, so a regular 15
+//! minutes grid takes one bit per reading, and `f32` values are XORed with the previous one,
+//! missing readings being a NaN. Each station id is written once, before its first block, and
+//! referred to by its number, in order of appearance. An index of the blocks at the end lets a
+//! reader jump to the readings of one station; an archive without one, cut short while writing,
+//! is indexed by scanning its blocks.
+//!
+//! Everything is little-endian, strings are a `u16` length followed by UTF-8:
+//!
+//! ```text
+//! header   "ALRTARCH" | version u16 | variable code, name and unit
+//! station  'S' | station id
+//! block    'B' | station u32 | count u32 | first time u32 | last time u32 | first value u32
+//!          | payload length u32 | payload
+//! index    'I' | stations u32 | per station: offset u64 of its 'S'
+//!          | blocks u32 | per block: station u32 | offset u64 | count u32 | first u32 | last u32
+//! footer   index offset u64 | "ALRTINDX"
+//! ```
+
+mod bits;
+mod block;
+mod reader;
+mod writer;
+
+use std::io::{self, Read, Write};
+
+use chrono::{DateTime, Utc};
+
+pub use crate::storage::archive::{reader::ArchiveReader, writer::ArchiveWriter};
+use crate::{model::StationId, storage::StorageError};
+
+/// Version written in the header, readers reject any other.
+pub const ARCHIVE_VERSION: u16 = 2;
+/// Readings per block, about six weeks of 15 minutes slots.
+pub const DEFAULT_BLOCK_SIZE: u32 = 4096;
+
+const MAGIC: &[u8; 8] = b"ALRTARCH";
+const FOOTER_MAGIC: &[u8; 8] = b"ALRTINDX";
+const FOOTER_LEN: i64 = 16;
+const STATION_TAG: u8 = b'S';
+const BLOCK_TAG: u8 = b'B';
+const INDEX_TAG: u8 = b'I';
+/// Bytes of a block before its payload, after the tag.
+const BLOCK_HEADER_LEN: u64 = 24;
+/// Bytes of a block entry of the index.
+const INDEX_ENTRY_LEN: u64 = 24;
+
+/// Where a block of one station is in the archive and the time range it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    station: StationId,
+    offset: u64,
+    count: u32,
+    first: u32,
+    last: u32,
+}
+
+impl BlockInfo {
+    pub fn station(&self) -> &StationId {
+        &self.station
+    }
+
+    /// Position of the block from the start of the archive.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Number of readings in the block.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn first(&self) -> DateTime<Utc> {
+        from_seconds(self.first)
+    }
+
+    pub fn last(&self) -> DateTime<Utc> {
+        from_seconds(self.last)
+    }
+}
+
+/// Stations and blocks of an archive.
+#[derive(Debug, Clone, Default)]
+struct Index {
+    /// Station ids by number, with the offset of their `'S'` record.
+    stations: Vec<(StationId, u64)>,
+    blocks: Vec<BlockInfo>,
+    /// End of the last complete record: where the index starts, or where appending resumes.
+    end: u64,
+}
+
+fn from_seconds(seconds: u32) -> DateTime<Utc> {
+    DateTime::from_timestamp(i64::from(seconds), 0).expect("any u32 of seconds is a valid time")
+}
+
+fn archive_error(source: io::Error) -> StorageError {
+    if source.kind() == io::ErrorKind::UnexpectedEof {
+        StorageError::Corrupt("archive is truncated".to_owned())
+    } else {
+        StorageError::Archive(source)
+    }
+}
+
+fn write_str(writer: &mut impl Write, value: &str) -> io::Result<()> {
+    let len = u16::try_from(value.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "string too long"))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(value.as_bytes())
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], StorageError> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes).map_err(archive_error)?;
+    Ok(bytes)
+}
+
+fn read_u16(reader: &mut impl Read) -> Result<u16, StorageError> {
+    read_array(reader).map(u16::from_le_bytes)
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, StorageError> {
+    read_array(reader).map(u32::from_le_bytes)
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, StorageError> {
+    read_array(reader).map(u64::from_le_bytes)
+}
+
+/// Reads `len` bytes, growing the buffer as they come so that a corrupt length can't allocate
+/// more than the reader holds.
+fn read_bytes(reader: &mut impl Read, len: u64) -> Result<Vec<u8>, StorageError> {
+    let mut bytes = Vec::new();
+    reader
+        .take(len)
+        .read_to_end(&mut bytes)
+        .map_err(archive_error)?;
+    if (bytes.len() as u64) < len {
+        return Err(archive_error(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(bytes)
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String, StorageError> {
+    let len = read_u16(reader)?;
+    String::from_utf8(read_bytes(reader, len.into())?)
+        .map_err(|_| StorageError::Corrupt("string is not UTF-8".to_owned()))
+}
+
+fn read_station(reader: &mut impl Read) -> Result<StationId, StorageError> {
+    read_string(reader)?
+        .parse::<StationId>()
+        .map_err(|error| StorageError::Corrupt(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{File, OpenOptions},
+        io::Cursor,
+        path::PathBuf,
+    };
+
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::{api::Variable, model::Reading};
+
+    fn station(n: u32) -> StationId {
+        format!("-/{n},1/simnbo").parse().unwrap()
+    }
+
+    fn time(minutes: i64) -> DateTime<Utc> {
+        from_seconds(1_700_000_000) + TimeDelta::minutes(minutes)
+    }
+
+    /// Ten readings 15 minutes apart for each of three stations, interleaved like snapshots.
+    fn readings() -> Vec<Reading> {
+        (0..10)
+            .flat_map(|slot| {
+                (1..=3).map(move |n| {
+                    let value = (slot % 4 != 3).then_some(n as f32 + slot as f32 / 10.0);
+                    Reading::new(station(n), time(slot * 15), value)
+                })
+            })
+            .collect()
+    }
+
+    fn write(readings: &[Reading]) -> ArchiveWriter<Vec<u8>> {
+        let mut writer = ArchiveWriter::new(Vec::new(), &Variable::default())
+            .unwrap()
+            .block_size(4);
+        for reading in readings {
+            writer.append(reading).unwrap();
+        }
+        writer
+    }
+
+    fn archive() -> Vec<u8> {
+        write(&readings()).finish().unwrap()
+    }
+
+    fn sorted(mut readings: Vec<Reading>) -> Vec<Reading> {
+        readings.sort_by(|a, b| {
+            (a.station().as_str(), a.time()).cmp(&(b.station().as_str(), b.time()))
+        });
+        readings
+    }
+
+    /// Everything a reader can get out of `bytes`, through the stream and through the index.
+    fn read_all(bytes: &[u8]) -> Result<(Vec<Reading>, Vec<Reading>), StorageError> {
+        let streamed = ArchiveReader::new(bytes)?.collect::<Result<Vec<_>, _>>()?;
+        let mut reader = ArchiveReader::new(Cursor::new(bytes))?;
+        let mut indexed = Vec::new();
+        for station in reader.stations()? {
+            indexed.extend(reader.readings(&station, time(0), time(1000))?);
+        }
+        Ok((streamed, indexed))
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("alert_core_{name}_{}.archive", std::process::id()))
+    }
+
+    fn open(path: &PathBuf) -> File {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .unwrap()
+    }
+
+    #[test]
+    fn reads_back_every_station() {
+        let bytes = archive();
+        let mut reader = ArchiveReader::new(Cursor::new(&bytes)).unwrap();
+
+        assert_eq!(reader.variable(), &Variable::default());
+        assert_eq!(
+            reader.stations().unwrap(),
+            [station(1), station(2), station(3)]
+        );
+        assert_eq!(reader.index().unwrap().len(), 9);
+        let (streamed, indexed) = read_all(&bytes).unwrap();
+        assert_eq!(sorted(streamed), sorted(readings()));
+        assert_eq!(indexed, sorted(readings()));
+    }
+
+    #[test]
+    fn reads_ranges_of_one_station() {
+        let mut reader = ArchiveReader::new(Cursor::new(archive())).unwrap();
+
+        let range = reader.readings(&station(2), time(40), time(90)).unwrap();
+        let times = range.iter().map(Reading::time).collect::<Vec<_>>();
+        assert_eq!(times, [time(45), time(60), time(75), time(90)]);
+        assert!(range.iter().all(|reading| reading.station() == &station(2)));
+        assert!(
+            reader
+                .readings(&station(9), time(0), time(90))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            reader
+                .readings(&station(1), time(200), time(300))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn stores_station_ids_once() {
+        let bytes = archive();
+        let id = station(1);
+        let occurrences = bytes
+            .windows(id.as_str().len())
+            .filter(|window| *window == id.as_str().as_bytes())
+            .count();
+        assert_eq!(occurrences, 1);
+    }
+
+    #[test]
+    fn rejects_unordered_readings() {
+        let mut writer = write(&readings()[..3]);
+        let reading = Reading::new(station(1), time(0), None);
+
+        assert!(matches!(
+            writer.append(&reading),
+            Err(StorageError::Unordered { .. })
+        ));
+        writer.flush().unwrap();
+        assert!(matches!(
+            writer.append(&reading),
+            Err(StorageError::Unordered { .. })
+        ));
+    }
+
+    #[test]
+    fn truncated_archives_keep_their_complete_blocks() {
+        let bytes = archive();
+        let (all, _) = read_all(&bytes).unwrap();
+
+        for len in 0..bytes.len() {
+            let cut = &bytes[..len];
+            let streamed = ArchiveReader::new(cut)
+                .map(|reader| reader.map_while(Result::ok).collect::<Vec<_>>())
+                .unwrap_or_default();
+            assert_eq!(streamed, all[..streamed.len()], "cut at {len}");
+
+            if let Ok(mut reader) = ArchiveReader::new(Cursor::new(cut)) {
+                let blocks = reader.index().unwrap().to_vec();
+                let recovered = blocks.iter().map(|info| info.count as usize).sum::<usize>();
+                assert!(recovered <= streamed.len(), "cut at {len}");
+                for info in blocks {
+                    let readings = reader.readings(info.station(), info.first(), info.last());
+                    assert_eq!(readings.unwrap().len(), info.count as usize);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flipped_bytes_never_panic() {
+        let bytes = archive();
+        for position in 0..bytes.len() {
+            let mut corrupt = bytes.clone();
+            corrupt[position] ^= 0xff;
+            let _ = read_all(&corrupt);
+        }
+    }
+
+    #[test]
+    fn flipped_sizes_are_errors() {
+        let bytes = archive();
+        let mut reader = ArchiveReader::new(Cursor::new(&bytes)).unwrap();
+        let block = reader.index().unwrap()[0].offset as usize;
+        let entry = bytes.len() - FOOTER_LEN as usize - 1;
+        let footer = bytes.len() - FOOTER_LEN as usize;
+
+        // Station number, count and payload length of the first block, the last time of the
+        // last index entry, then the index offset.
+        for position in [
+            block + 1,
+            block + 5,
+            block + 8,
+            block + 21,
+            block + 24,
+            entry,
+            footer,
+        ] {
+            let mut corrupt = bytes.clone();
+            corrupt[position] ^= 0xff;
+            assert!(read_all(&corrupt).is_err(), "flipped byte {position}");
+        }
+    }
+
+    #[test]
+    fn recovers_an_unfinished_archive() {
+        let mut writer = write(&readings());
+        writer.flush().unwrap();
+        let mut bytes = writer.get_ref().clone();
+        // A block cut short by a crash.
+        bytes.extend_from_slice(&[BLOCK_TAG, 0, 0, 0]);
+
+        let mut reader = ArchiveReader::new(Cursor::new(&bytes)).unwrap();
+        assert_eq!(reader.index().unwrap().len(), 9);
+        let readings = reader.readings(&station(3), time(0), time(1000)).unwrap();
+        assert_eq!(readings.len(), 10);
+    }
+
+    #[test]
+    fn reopens_to_append() {
+        let path = temp_file("reopen");
+        let readings = readings();
+        let (old, new) = readings.split_at(15);
+
+        let mut writer = ArchiveWriter::new(File::create(&path).unwrap(), &Variable::default())
+            .unwrap()
+            .block_size(4);
+        for reading in old {
+            writer.append(reading).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut writer = ArchiveWriter::reopen(open(&path)).unwrap();
+        assert!(matches!(
+            writer.append(&old[0]),
+            Err(StorageError::Unordered { .. })
+        ));
+        for reading in new {
+            writer.append(reading).unwrap();
+        }
+        writer
+            .append(&Reading::new(station(4), time(0), Some(4.0)))
+            .unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        // Unfinished, then finished after being reopened again.
+        let writer = ArchiveWriter::reopen(open(&path)).unwrap();
+        writer.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let (streamed, indexed) = read_all(&bytes).unwrap();
+        let mut expected = readings.clone();
+        expected.push(Reading::new(station(4), time(0), Some(4.0)));
+        assert_eq!(sorted(streamed), sorted(expected.clone()));
+        assert_eq!(indexed, sorted(expected));
+    }
+}
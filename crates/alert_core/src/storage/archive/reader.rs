@@ -0,0 +1,416 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    vec,
+};
+
+use chrono::{DateTime, TimeZone};
+
+use crate::{
+    api::Variable,
+    model::{Reading, StationId},
+    storage::{
+        StorageError,
+        archive::{
+            ARCHIVE_VERSION, BLOCK_HEADER_LEN, BLOCK_TAG, BlockInfo, FOOTER_LEN, FOOTER_MAGIC,
+            INDEX_ENTRY_LEN, INDEX_TAG, Index, MAGIC, STATION_TAG, archive_error,
+            block::{self, EncodedBlock},
+            from_seconds, read_array, read_bytes, read_station, read_string, read_u16, read_u32,
+            read_u64,
+        },
+    },
+};
+
+/// Reads an archive written by an [`ArchiveWriter`](crate::storage::ArchiveWriter).
+///
+/// Iterating streams every reading block by block, in the order they were written, and stops at
+/// the index; an archive cut short yields the complete blocks before the cut, then an error.
+/// With a seekable reader [`ArchiveReader::readings`] only decodes the blocks it needs.
+pub struct ArchiveReader<R> {
+    reader: R,
+    variable: Variable,
+    /// Bytes of the header, where the first record starts.
+    header_len: u64,
+    pending: vec::IntoIter<Reading>,
+    done: bool,
+    /// Stations met so far while streaming, by number.
+    streamed: Vec<StationId>,
+    index: Option<Index>,
+}
+
+impl<R> ArchiveReader<R>
+where
+    R: Read,
+{
+    /// Reads the header, rejecting archives of other versions.
+    pub fn new(mut reader: R) -> Result<Self, StorageError> {
+        if &read_array::<8>(&mut reader)? != MAGIC {
+            return Err(StorageError::Corrupt("not an archive".to_owned()));
+        }
+        let version = read_u16(&mut reader)?;
+        if version != ARCHIVE_VERSION {
+            return Err(StorageError::UnsupportedVersion(version));
+        }
+        let code = read_string(&mut reader)?;
+        let name = read_string(&mut reader)?;
+        let unit = read_string(&mut reader)?;
+        let header_len = (MAGIC.len() + 2 + 6 + code.len() + name.len() + unit.len()) as u64;
+
+        Ok(Self {
+            reader,
+            variable: Variable::new(code, name, unit),
+            header_len,
+            pending: Vec::new().into_iter(),
+            done: false,
+            streamed: Vec::new(),
+            index: None,
+        })
+    }
+
+    /// Variable of the readings in the archive.
+    pub fn variable(&self) -> &Variable {
+        &self.variable
+    }
+
+    /// Reads the next block in the stream, `None` at the index or at the end of a truncated
+    /// archive.
+    pub fn next_block(&mut self) -> Result<Option<Vec<Reading>>, StorageError> {
+        loop {
+            if self.done {
+                return Ok(None);
+            }
+            let mut tag = [0];
+            let read = self.reader.read(&mut tag).map_err(archive_error);
+            let result = match read {
+                Ok(1) if tag[0] == STATION_TAG => match read_station(&mut self.reader) {
+                    Ok(station) => {
+                        self.streamed.push(station);
+                        continue;
+                    }
+                    Err(error) => Err(error),
+                },
+                Ok(1) if tag[0] == BLOCK_TAG => {
+                    read_block(&mut self.reader, &self.streamed).map(Some)
+                }
+                Ok(0) => Ok(None),
+                Ok(_) if tag[0] == INDEX_TAG => Ok(None),
+                Ok(_) => Err(StorageError::Corrupt(format!(
+                    "unexpected section {:?}",
+                    tag[0] as char
+                ))),
+                Err(error) => Err(error),
+            };
+            self.done = !matches!(result, Ok(Some(_)));
+            return result;
+        }
+    }
+}
+
+impl<R> ArchiveReader<R>
+where
+    R: Read + Seek,
+{
+    /// Blocks of the archive, read from the index at its end or, for an archive that wasn't
+    /// finished, by scanning its blocks.
+    pub fn index(&mut self) -> Result<&[BlockInfo], StorageError> {
+        Ok(&self.load_index()?.blocks)
+    }
+
+    /// The whole index, for reopening the archive to append to it.
+    pub(super) fn into_index(mut self) -> Result<Index, StorageError> {
+        self.load_index()?;
+        Ok(self.index.take().expect("loaded above"))
+    }
+
+    fn load_index(&mut self) -> Result<&Index, StorageError> {
+        if self.index.is_none() {
+            let position = self.reader.stream_position().map_err(archive_error)?;
+            let index = match self.read_index() {
+                Ok(Some(index)) => Ok(index),
+                Ok(None) => self.scan_index(),
+                Err(error) => Err(error),
+            };
+            self.reader
+                .seek(SeekFrom::Start(position))
+                .map_err(archive_error)?;
+            self.index = Some(index?);
+        }
+        Ok(self.index.as_ref().expect("loaded above"))
+    }
+
+    /// Reads the index the footer points to, `None` without footer.
+    fn read_index(&mut self) -> Result<Option<Index>, StorageError> {
+        let invalid = |what: &str| StorageError::Corrupt(format!("invalid index: {what}"));
+        let reader = &mut self.reader;
+        let len = reader.seek(SeekFrom::End(0)).map_err(archive_error)?;
+        if len < self.header_len + FOOTER_LEN as u64 {
+            return Ok(None);
+        }
+        reader
+            .seek(SeekFrom::End(-FOOTER_LEN))
+            .map_err(archive_error)?;
+        let offset = read_u64(reader)?;
+        if &read_array::<8>(reader)? != FOOTER_MAGIC {
+            return Ok(None);
+        }
+
+        // Room for the index between its offset and the footer, past the tag.
+        let mut room = (len - FOOTER_LEN as u64)
+            .checked_sub(offset)
+            .filter(|_| offset >= self.header_len)
+            .and_then(|room| room.checked_sub(1))
+            .ok_or_else(|| invalid("offset out of the archive"))?;
+        reader
+            .seek(SeekFrom::Start(offset))
+            .map_err(archive_error)?;
+        if read_array::<1>(reader)? != [INDEX_TAG] {
+            return Err(invalid("offset doesn't point to it"));
+        }
+        let mut counted = |count: u32, entry_len: u64| {
+            room = room
+                .checked_sub(4 + u64::from(count) * entry_len)
+                .ok_or_else(|| invalid("more entries than bytes"))?;
+            Ok::<_, StorageError>(count)
+        };
+        let stations = counted(read_u32(reader)?, 8)?;
+        let station_offsets = (0..stations)
+            .map(|_| read_u64(reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        let blocks = counted(read_u32(reader)?, INDEX_ENTRY_LEN)?;
+        let entries = (0..blocks)
+            .map(|_| {
+                Ok((
+                    read_u32(reader)?,
+                    read_u64(reader)?,
+                    read_u32(reader)?,
+                    read_u32(reader)?,
+                    read_u32(reader)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, StorageError>>()?;
+
+        let mut index = Index {
+            end: offset,
+            ..Index::default()
+        };
+        for station_offset in station_offsets {
+            if station_offset < self.header_len || station_offset >= offset {
+                return Err(invalid("station out of the archive"));
+            }
+            reader
+                .seek(SeekFrom::Start(station_offset))
+                .map_err(archive_error)?;
+            if read_array::<1>(reader)? != [STATION_TAG] {
+                return Err(invalid("station offset doesn't point to a station"));
+            }
+            index.stations.push((read_station(reader)?, station_offset));
+        }
+        for (number, block_offset, count, first, last) in entries {
+            let (station, _) = index
+                .stations
+                .get(number as usize)
+                .ok_or_else(|| invalid("unknown station"))?;
+            if block_offset < self.header_len || block_offset >= offset {
+                return Err(invalid("block out of the archive"));
+            }
+            index.blocks.push(BlockInfo {
+                station: station.clone(),
+                offset: block_offset,
+                count,
+                first,
+                last,
+            });
+        }
+        Ok(Some(index))
+    }
+
+    /// Rebuilds the index of an archive without footer from its records, up to the last
+    /// complete one.
+    fn scan_index(&mut self) -> Result<Index, StorageError> {
+        let len = self.reader.seek(SeekFrom::End(0)).map_err(archive_error)?;
+        self.reader
+            .seek(SeekFrom::Start(self.header_len))
+            .map_err(archive_error)?;
+        let mut index = Index {
+            end: self.header_len,
+            ..Index::default()
+        };
+        while self.scan_record(len - index.end, &mut index)? {
+            index.end = self.reader.stream_position().map_err(archive_error)?;
+        }
+        Ok(index)
+    }
+
+    /// Adds the record at `index.end` to the index, `false` at the end of the records or if
+    /// the `remaining` bytes can't hold it.
+    fn scan_record(&mut self, remaining: u64, index: &mut Index) -> Result<bool, StorageError> {
+        let offset = index.end;
+        let reader = &mut self.reader;
+        if remaining == 0 {
+            return Ok(false);
+        }
+        match read_array::<1>(reader)?[0] {
+            STATION_TAG if remaining >= 3 => {
+                let len = read_u16(reader)?;
+                if remaining < 3 + u64::from(len) {
+                    return Ok(false);
+                }
+                // Back to the length, read again with the id.
+                reader.seek(SeekFrom::Current(-2)).map_err(archive_error)?;
+                index.stations.push((read_station(reader)?, offset));
+            }
+            BLOCK_TAG if remaining > BLOCK_HEADER_LEN => {
+                let number = read_u32(reader)?;
+                let count = read_u32(reader)?;
+                let first = read_u32(reader)?;
+                let last = read_u32(reader)?;
+                let _first_value = read_u32(reader)?;
+                let payload_len = read_u32(reader)?;
+                let (station, _) = index.stations.get(number as usize).ok_or_else(|| {
+                    StorageError::Corrupt(format!("block of unknown station {number}"))
+                })?;
+                block::check_sizes(count, payload_len)?;
+                if remaining < 1 + BLOCK_HEADER_LEN + u64::from(payload_len) {
+                    return Ok(false);
+                }
+                reader
+                    .seek(SeekFrom::Current(payload_len.into()))
+                    .map_err(archive_error)?;
+                index.blocks.push(BlockInfo {
+                    station: station.clone(),
+                    offset,
+                    count,
+                    first,
+                    last,
+                });
+            }
+            STATION_TAG | BLOCK_TAG | INDEX_TAG => return Ok(false),
+            tag => {
+                return Err(StorageError::Corrupt(format!(
+                    "unexpected section {:?}",
+                    tag as char
+                )));
+            }
+        }
+        Ok(true)
+    }
+
+    /// Stations with readings in the archive, in the order they first appear.
+    pub fn stations(&mut self) -> Result<Vec<StationId>, StorageError> {
+        let index = self.load_index()?;
+        Ok(index
+            .stations
+            .iter()
+            .map(|(station, _)| station.clone())
+            .collect())
+    }
+
+    /// Readings of `station` from `start` to `end`, both included, in chronological order.
+    pub fn readings<T>(
+        &mut self,
+        station: &StationId,
+        start: DateTime<T>,
+        end: DateTime<T>,
+    ) -> Result<Vec<Reading>, StorageError>
+    where
+        T: TimeZone,
+    {
+        let (start, end) = (start.to_utc(), end.to_utc());
+        let index = self.load_index()?;
+        let Some(number) = index.stations.iter().position(|(id, _)| id == station) else {
+            return Ok(Vec::new());
+        };
+        let mut blocks = index
+            .blocks
+            .iter()
+            .filter(|info| &info.station == station && info.first() <= end && info.last() >= start)
+            .map(|info| (info.first, info.offset, info.count, info.last))
+            .collect::<Vec<_>>();
+        blocks.sort_unstable();
+
+        let position = self.reader.stream_position().map_err(archive_error)?;
+        let mut readings = Vec::new();
+        for (first, offset, count, last) in blocks {
+            self.reader
+                .seek(SeekFrom::Start(offset))
+                .map_err(archive_error)?;
+            if read_array::<1>(&mut self.reader)? != [BLOCK_TAG] {
+                return Err(StorageError::Corrupt("invalid block offset".to_owned()));
+            }
+            let (block_station, block) = read_encoded(&mut self.reader)?;
+            if block_station as usize != number
+                || (block.count, block.first_time, block.last_time) != (count, first, last)
+            {
+                return Err(StorageError::Corrupt(
+                    "index doesn't match the block it points to".to_owned(),
+                ));
+            }
+            readings.extend(
+                block
+                    .decode()?
+                    .into_iter()
+                    .map(|(time, value)| Reading::new(station.clone(), from_seconds(time), value))
+                    .filter(|reading| (start..=end).contains(&reading.time())),
+            );
+        }
+        self.reader
+            .seek(SeekFrom::Start(position))
+            .map_err(archive_error)?;
+        Ok(readings)
+    }
+}
+
+impl<R> Iterator for ArchiveReader<R>
+where
+    R: Read,
+{
+    type Item = Result<Reading, StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(reading) = self.pending.next() {
+                return Some(Ok(reading));
+            }
+            match self.next_block() {
+                Ok(Some(block)) => self.pending = block.into_iter(),
+                Ok(None) => return None,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+/// Reads a block, its tag already consumed, returning the number of its station.
+fn read_encoded(reader: &mut impl Read) -> Result<(u32, EncodedBlock), StorageError> {
+    let station = read_u32(reader)?;
+    let count = read_u32(reader)?;
+    let first_time = read_u32(reader)?;
+    let last_time = read_u32(reader)?;
+    let first_value = read_u32(reader)?;
+    let payload_len = read_u32(reader)?;
+    block::check_sizes(count, payload_len)?;
+    let block = EncodedBlock {
+        count,
+        first_time,
+        last_time,
+        first_value,
+        payload: read_bytes(reader, payload_len.into())?,
+    };
+    Ok((station, block))
+}
+
+/// Reads and decodes a block, its tag already consumed, naming its station from `stations`.
+fn read_block(
+    reader: &mut impl Read,
+    stations: &[StationId],
+) -> Result<Vec<Reading>, StorageError> {
+    let (number, block) = read_encoded(reader)?;
+    let station = stations
+        .get(number as usize)
+        .ok_or_else(|| StorageError::Corrupt(format!("block of unknown station {number}")))?;
+
+    Ok(block
+        .decode()?
+        .into_iter()
+        .map(|(time, value)| Reading::new(station.clone(), from_seconds(time), value))
+        .collect())
+}
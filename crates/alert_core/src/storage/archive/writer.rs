@@ -0,0 +1,298 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Seek, SeekFrom, Write},
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::{
+    api::Variable,
+    model::{Reading, StationId, Stations, TimeSeries},
+    storage::{
+        StorageError,
+        archive::{
+            ARCHIVE_VERSION, ArchiveReader, BLOCK_TAG, BlockInfo, DEFAULT_BLOCK_SIZE, FOOTER_MAGIC,
+            INDEX_TAG, Index, MAGIC, STATION_TAG, archive_error, block::BlockEncoder, write_str,
+        },
+    },
+};
+
+/// Streams readings of one variable into an archive.
+///
+/// Each station keeps a block open until it's full, so readings of different stations can be
+/// interleaved, but those of a station have to come in chronological order. Times are stored as
+/// whole seconds, milliseconds are dropped. Open blocks are only written by
+/// [`ArchiveWriter::flush`] and [`ArchiveWriter::finish`], which also writes the index.
+pub struct ArchiveWriter<W: Write> {
+    writer: W,
+    /// Bytes written so far, the offset of the next record.
+    offset: u64,
+    block_size: u32,
+    open: HashMap<StationId, BlockEncoder>,
+    index: Index,
+    /// Number of every station written so far.
+    numbers: HashMap<StationId, u32>,
+    /// Last time of the blocks written so far, by station.
+    last_times: HashMap<StationId, u32>,
+}
+
+impl<W> ArchiveWriter<W>
+where
+    W: Write,
+{
+    /// Writes the header of an archive of `variable` readings.
+    pub fn new(mut writer: W, variable: &Variable) -> Result<Self, StorageError> {
+        let mut header = Vec::new();
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&ARCHIVE_VERSION.to_le_bytes());
+        for value in [variable.code(), variable.name(), variable.unit()] {
+            write_str(&mut header, value).map_err(archive_error)?;
+        }
+        writer.write_all(&header).map_err(archive_error)?;
+
+        Ok(Self::resume(
+            writer,
+            Index {
+                end: header.len() as u64,
+                ..Index::default()
+            },
+        ))
+    }
+
+    /// Writer carrying on after the records of `index`.
+    fn resume(writer: W, index: Index) -> Self {
+        let numbers = (0..)
+            .zip(&index.stations)
+            .map(|(number, (station, _))| (station.clone(), number))
+            .collect();
+        let mut last_times = HashMap::<StationId, u32>::new();
+        for info in &index.blocks {
+            let last = last_times.entry(info.station.clone()).or_default();
+            *last = info.last.max(*last);
+        }
+        Self {
+            writer,
+            offset: index.end,
+            block_size: DEFAULT_BLOCK_SIZE,
+            open: HashMap::new(),
+            index,
+            numbers,
+            last_times,
+        }
+    }
+
+    /// Readings per block: bigger blocks compress a bit better, smaller ones make range reads
+    /// cheaper.
+    pub fn block_size(mut self, block_size: u32) -> Self {
+        self.block_size = block_size.max(1);
+        self
+    }
+
+    pub fn append(&mut self, reading: &Reading) -> Result<(), StorageError> {
+        self.push(reading.station(), reading.time(), reading.value())
+    }
+
+    /// Appends the readings of the snapshot of the slot starting at `time`.
+    pub fn append_stations<T>(
+        &mut self,
+        time: DateTime<T>,
+        stations: &Stations,
+    ) -> Result<(), StorageError>
+    where
+        T: TimeZone,
+    {
+        let time = time.to_utc();
+        for station in stations.iter() {
+            self.push(station.idstazione(), time, station.value().copied())?;
+        }
+        Ok(())
+    }
+
+    /// Appends the readings of a time series of `station`, narrowed to `f32`.
+    pub fn append_timeseries(
+        &mut self,
+        station: &StationId,
+        series: &TimeSeries,
+    ) -> Result<(), StorageError> {
+        for tv in series.iter() {
+            self.push(station, tv.time(), tv.value().map(|value| value as f32))?;
+        }
+        Ok(())
+    }
+
+    fn push(
+        &mut self,
+        station: &StationId,
+        time: DateTime<Utc>,
+        value: Option<f32>,
+    ) -> Result<(), StorageError> {
+        let seconds =
+            u32::try_from(time.timestamp()).map_err(|_| StorageError::OutOfRange(time))?;
+        let unordered = || StorageError::Unordered {
+            station: station.to_string(),
+            time,
+        };
+
+        let Some(block) = self.open.get_mut(station) else {
+            if self
+                .last_times
+                .get(station)
+                .is_some_and(|&last| seconds <= last)
+            {
+                return Err(unordered());
+            }
+            self.open
+                .insert(station.clone(), BlockEncoder::new(seconds, value));
+            return Ok(());
+        };
+        if seconds <= block.last_time() {
+            return Err(unordered());
+        }
+        block.push(seconds, value);
+
+        if block.count() >= self.block_size {
+            let block = self.open.remove(station).expect("found above");
+            self.write_block(station.clone(), block)?;
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self, station: StationId, block: BlockEncoder) -> Result<(), StorageError> {
+        let number = match self.numbers.get(&station) {
+            Some(&number) => number,
+            None => self.write_station(&station)?,
+        };
+        let block = block.finish();
+        let payload_len = u32::try_from(block.payload.len())
+            .map_err(|_| StorageError::Corrupt("block too large".to_owned()))?;
+
+        let mut bytes = Vec::with_capacity(block.payload.len() + 32);
+        bytes.push(BLOCK_TAG);
+        for value in [
+            number,
+            block.count,
+            block.first_time,
+            block.last_time,
+            block.first_value,
+            payload_len,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&block.payload);
+        let offset = self.offset;
+        self.write_all(&bytes)?;
+
+        self.last_times.insert(station.clone(), block.last_time);
+        self.index.blocks.push(BlockInfo {
+            station,
+            offset,
+            count: block.count,
+            first: block.first_time,
+            last: block.last_time,
+        });
+        Ok(())
+    }
+
+    /// Writes the record of a new station, returning its number.
+    fn write_station(&mut self, station: &StationId) -> Result<u32, StorageError> {
+        let number = u32::try_from(self.index.stations.len())
+            .map_err(|_| StorageError::Corrupt("too many stations".to_owned()))?;
+        let mut bytes = vec![STATION_TAG];
+        write_str(&mut bytes, station.as_str()).map_err(archive_error)?;
+        let offset = self.offset;
+        self.write_all(&bytes)?;
+
+        self.index.stations.push((station.clone(), offset));
+        self.numbers.insert(station.clone(), number);
+        Ok(number)
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), StorageError> {
+        self.writer.write_all(bytes).map_err(archive_error)?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn write_open(&mut self) -> Result<(), StorageError> {
+        let mut open = std::mem::take(&mut self.open)
+            .into_iter()
+            .collect::<Vec<_>>();
+        open.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+        for (station, block) in open {
+            self.write_block(station, block)?;
+        }
+        Ok(())
+    }
+
+    /// Bytes written so far plus the payloads of the blocks still open.
+    pub fn len(&self) -> u64 {
+        self.offset
+            + self
+                .open
+                .values()
+                .map(|block| block.len() as u64)
+                .sum::<u64>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.blocks.is_empty() && self.open.is_empty()
+    }
+
+    /// Writes the open blocks and flushes the inner writer, so that a crash doesn't lose the
+    /// readings appended so far: an archive left without index is read by scanning its blocks.
+    /// The next readings of every station start a new block.
+    pub fn flush(&mut self) -> Result<(), StorageError> {
+        self.write_open()?;
+        self.writer.flush().map_err(archive_error)
+    }
+
+    /// Writes the open blocks, the index and the footer, returning the inner writer.
+    pub fn finish(mut self) -> Result<W, StorageError> {
+        self.write_open()?;
+
+        let index_offset = self.offset;
+        let mut bytes = vec![INDEX_TAG];
+        let too_many = |_| StorageError::Corrupt("too many blocks".to_owned());
+        let stations = u32::try_from(self.index.stations.len()).map_err(too_many)?;
+        bytes.extend_from_slice(&stations.to_le_bytes());
+        for (_, offset) in &self.index.stations {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+        }
+        let blocks = u32::try_from(self.index.blocks.len()).map_err(too_many)?;
+        bytes.extend_from_slice(&blocks.to_le_bytes());
+        for info in &self.index.blocks {
+            bytes.extend_from_slice(&self.numbers[&info.station].to_le_bytes());
+            bytes.extend_from_slice(&info.offset.to_le_bytes());
+            for value in [info.count, info.first, info.last] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&index_offset.to_le_bytes());
+        bytes.extend_from_slice(FOOTER_MAGIC);
+        self.write_all(&bytes)?;
+
+        self.writer.flush().map_err(archive_error)?;
+        Ok(self.writer)
+    }
+
+    /// The inner writer, with whatever was written so far.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+}
+
+impl ArchiveWriter<File> {
+    /// Reopens an archive to append readings to it, `file` being open for reading and writing.
+    ///
+    /// The index is dropped, [`ArchiveWriter::finish`] writes a new one for the old and new
+    /// blocks; an archive cut short loses its incomplete last block. Readings of a station have
+    /// to come after those already in the archive.
+    pub fn reopen(mut file: File) -> Result<Self, StorageError> {
+        let index = ArchiveReader::new(BufReader::new(&file))?.into_index()?;
+        file.set_len(index.end).map_err(archive_error)?;
+        file.seek(SeekFrom::Start(index.end))
+            .map_err(archive_error)?;
+        Ok(Self::resume(file, index))
+    }
+}
@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+
+#[derive(thiserror::Error, Debug)]
+pub enum StorageError {
+    #[error("Couldn't access {path}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Couldn't encode snapshot")]
+    Encode(#[from] serde_json::Error),
+    #[error("Couldn't reach {address}")]
+    Network {
+        address: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Request to {address} failed")]
+    Http {
+        address: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("Server rejected the data with status {status}: {message}")]
+    Rejected { status: u16, message: String },
+    #[error("Stored data is not valid: {0}")]
+    Corrupt(String),
+    #[error("Archive version {0} is not supported")]
+    UnsupportedVersion(u16),
+    #[error("Reading of {station} at {time} is not after the previous one")]
+    Unordered {
+        station: String,
+        time: DateTime<Utc>,
+    },
+    #[error("{0} can't be stored in an archive")]
+    OutOfRange(DateTime<Utc>),
+    #[error("Couldn't read or write the archive")]
+    Archive(#[source] std::io::Error),
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite query failed")]
+    Sqlite(#[from] rusqlite::Error),
+}
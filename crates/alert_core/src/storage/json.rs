@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::Variable,
+    model::Stations,
+    storage::{SnapshotStore, StorageError},
+};
+
+/// Stores each snapshot as `<variable>/stations_<unix millis>.json` in a directory, the layout
+/// read by [`FixtureSource`](crate::source::FixtureSource), so a scraped directory can be replayed
+/// as is. The subdirectory is the variable code reduced to lowercase alphanumeric runs, e.g.
+/// `254_0_0_1_b13215` for [`Variable::HYDROMETRIC_LEVEL`], so that the snapshots of different
+/// variables never overwrite each other.
+///
+/// Files hold `{"variable": ..., "stations": [...]}`, the stations as sent by upstream next to
+/// the variable they were fetched for, which a plain [`Stations`] array would lose.
+#[derive(Clone, Debug)]
+pub struct JsonDirStore {
+    dir: PathBuf,
+}
+
+impl JsonDirStore {
+    /// Uses `dir`, creating it if missing.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, StorageError> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|source| StorageError::Io {
+            path: dir.clone(),
+            source,
+        })?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Directory holding the snapshots of `variable`.
+    pub fn variable_dir(&self, variable: &Variable) -> PathBuf {
+        self.dir.join(slug(variable.code()))
+    }
+
+    /// Path of the snapshot of `variable` for the slot starting at `time`.
+    pub fn path(&self, variable: &Variable, time: DateTime<Utc>) -> PathBuf {
+        self.variable_dir(variable)
+            .join(format!("stations_{}.json", time.timestamp_millis()))
+    }
+
+    /// Reads back the snapshot of `variable` for the slot starting at `time`.
+    pub fn load(&self, variable: &Variable, time: DateTime<Utc>) -> Result<Stations, StorageError> {
+        let path = self.path(variable, time);
+        let body = std::fs::read(&path).map_err(|source| StorageError::Io { path, source })?;
+        let file: SnapshotFile<Stations> = serde_json::from_slice(&body)?;
+        Ok(file.stations.with_variable(file.variable))
+    }
+}
+
+/// Layout of the files written by [`JsonDirStore`].
+#[derive(Deserialize, Serialize)]
+pub(crate) struct SnapshotFile<T> {
+    pub(crate) variable: Variable,
+    pub(crate) stations: T,
+}
+
+impl SnapshotStore for JsonDirStore {
+    fn store(&mut self, time: DateTime<Utc>, stations: &Stations) -> Result<(), StorageError> {
+        let dir = self.variable_dir(stations.variable());
+        std::fs::create_dir_all(&dir).map_err(|source| StorageError::Io { path: dir, source })?;
+        let path = self.path(stations.variable(), time);
+        // Written aside and renamed, so that a crash never leaves a truncated snapshot behind.
+        let partial = path.with_extension("json.partial");
+        let body = serde_json::to_vec(&SnapshotFile {
+            variable: stations.variable().clone(),
+            stations,
+        })?;
+        std::fs::write(&partial, body)
+            .and_then(|()| std::fs::rename(&partial, &path))
+            .map_err(|source| StorageError::Io { path, source })
+    }
+
+    fn last_slot(&self, variable: &Variable) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let dir = self.variable_dir(variable);
+        let io_error = |source| StorageError::Io {
+            path: dir.clone(),
+            source,
+        };
+
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(io_error(error)),
+        };
+        let mut last = None;
+        for entry in entries {
+            let name = entry.map_err(io_error)?.file_name();
+            let slot = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("stations_"))
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|millis| millis.parse::<i64>().ok())
+                .and_then(DateTime::from_timestamp_millis);
+            last = last.max(slot);
+        }
+        Ok(last)
+    }
+}
+
+/// Lowercase alphanumeric runs of `value` joined by `_`, to use it in a file name.
+pub(crate) fn slug(value: &str) -> String {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::model::fixtures;
+
+    /// A fresh directory under the system temporary one.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("alert_core_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn stations(variable: Variable) -> Stations {
+        fixtures::stations(&[Some(1.5)]).with_variable(variable)
+    }
+
+    #[test]
+    fn snapshots_keep_their_variable() {
+        let dir = temp_dir("json_store");
+        let mut store = JsonDirStore::new(&dir).unwrap();
+        let variable = Variable::KNOWN[1].clone();
+        let time = DateTime::from_timestamp_millis(1_700_000_100_000).unwrap();
+
+        store.store(time, &stations(variable.clone())).unwrap();
+        let loaded = store.load(&variable, time).unwrap();
+
+        assert_eq!(loaded.variable(), &variable);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.iter().next().unwrap().value(), Some(&1.5));
+        assert!(
+            dir.join("1_0_900_1_b13011/stations_1700000100000.json")
+                .is_file()
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn last_slot_ignores_other_files_and_variables() {
+        let dir = temp_dir("json_store_last_slot");
+        let mut store = JsonDirStore::new(&dir).unwrap();
+        let level = Variable::HYDROMETRIC_LEVEL;
+        let rain = Variable::PRECIPITATION;
+        assert_eq!(store.last_slot(&level).unwrap(), None);
+
+        let first = DateTime::from_timestamp_millis(1_700_000_100_000).unwrap();
+        let second = DateTime::from_timestamp_millis(1_700_000_200_000).unwrap();
+        store.store(second, &stations(level.clone())).unwrap();
+        store.store(first, &stations(level.clone())).unwrap();
+        store
+            .store(second + TimeDelta::minutes(15), &stations(rain.clone()))
+            .unwrap();
+        let level_dir = store.variable_dir(&level);
+        std::fs::write(level_dir.join("stations_9999999999999.json.partial"), "").unwrap();
+        std::fs::write(level_dir.join("notes.json"), "").unwrap();
+
+        assert_eq!(store.last_slot(&level).unwrap(), Some(second));
+        assert_eq!(
+            store.last_slot(&rain).unwrap(),
+            Some(second + TimeDelta::minutes(15))
+        );
+        assert!(matches!(
+            store.load(&level, first - TimeDelta::minutes(15)),
+            Err(StorageError::Io { .. })
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn slug_keeps_alphanumeric_runs() {
+        assert_eq!(slug("-/1129579,4472121/simnbo"), "1129579_4472121_simnbo");
+        assert_eq!(slug("Castell'Arquato Canale"), "castell_arquato_canale");
+        assert_eq!(slug(Variable::HYDROMETRIC_LEVEL.code()), "254_0_0_1_b13215");
+    }
+}
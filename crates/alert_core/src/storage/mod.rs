@@ -0,0 +1,50 @@
+mod archive;
+mod error;
+mod json;
+mod questdb;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+use chrono::{DateTime, Utc};
+
+pub(crate) use crate::storage::json::{SnapshotFile, slug};
+#[cfg(feature = "sqlite")]
+pub use crate::storage::sqlite::{Scrape, SqliteStore};
+pub use crate::storage::{
+    archive::{ARCHIVE_VERSION, ArchiveReader, ArchiveWriter, BlockInfo, DEFAULT_BLOCK_SIZE},
+    error::StorageError,
+    json::JsonDirStore,
+    questdb::{IlpTransport, QuestDbWriter},
+};
+use crate::{api::Variable, model::Stations};
+
+/// Where scraped snapshots are persisted, one per slot.
+pub trait SnapshotStore {
+    /// Stores the snapshot of the slot starting at `time`, replacing any previous one.
+    fn store(&mut self, time: DateTime<Utc>, stations: &Stations) -> Result<(), StorageError>;
+
+    /// Most recent slot stored so far for `variable`, `None` when there is none yet.
+    fn last_slot(&self, variable: &Variable) -> Result<Option<DateTime<Utc>>, StorageError>;
+
+    /// Writes out anything buffered by [`SnapshotStore::store`].
+    fn flush(&mut self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+impl<S> SnapshotStore for Box<S>
+where
+    S: SnapshotStore + ?Sized,
+{
+    fn store(&mut self, time: DateTime<Utc>, stations: &Stations) -> Result<(), StorageError> {
+        (**self).store(time, stations)
+    }
+
+    fn last_slot(&self, variable: &Variable) -> Result<Option<DateTime<Utc>>, StorageError> {
+        (**self).last_slot(variable)
+    }
+
+    fn flush(&mut self) -> Result<(), StorageError> {
+        (**self).flush()
+    }
+}
@@ -0,0 +1,686 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Write as _},
+    io::{self, Write as _},
+    net::{TcpStream, ToSocketAddrs},
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use log::warn;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+
+use crate::{
+    api::Variable,
+    model::{StationId, Stations, TimeSeries},
+    storage::{SnapshotStore, StorageError},
+};
+
+const DEFAULT_STATIONS_TABLE: &str = "stations";
+const DEFAULT_TIMESERIES_TABLE: &str = "timeseries";
+/// Lines sent in a single batch, a snapshot is about 250 lines.
+const DEFAULT_BATCH_SIZE: usize = 5000;
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Port of the QuestDB REST API, which also takes ILP over HTTP.
+const DEFAULT_HTTP_PORT: u16 = 9000;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How lines reach QuestDB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IlpTransport {
+    /// Plain ILP over a long lived TCP connection, port 9009 by default. QuestDB doesn't
+    /// acknowledge lines here, rejected ones only show up in its logs.
+    Tcp,
+    /// One `POST /write` per batch, port 9000 by default. Rejected batches are reported.
+    Http,
+}
+
+/// Writes snapshots and time series to QuestDB with the InfluxDB Line Protocol.
+///
+/// Snapshots go to the stations table, one row per station tagged with its `station`, `name`
+/// and `variable` symbols, holding the `value` and the `soglia1`-`soglia3` thresholds; stations
+/// with neither are left out. Time series go to the time series table, tagged with `station`
+/// and `variable`, one `value` per reading.
+///
+/// Lines are only queued by [`SnapshotStore::store`] and sent in batches by
+/// [`SnapshotStore::flush`], which must be called before dropping the writer: unsent lines are
+/// lost. A batch that can't be sent stays queued for the next flush. [`SnapshotStore::last_slot`]
+/// asks the REST API for the latest row of the variable in the stations table.
+///
+/// All I/O is blocking, call the writer from a blocking thread (e.g.
+/// [`tokio::task::spawn_blocking`]) when running in an async runtime.
+pub struct QuestDbWriter {
+    address: String,
+    transport: IlpTransport,
+    http_address: String,
+    stations_table: String,
+    timeseries_table: String,
+    batch_size: usize,
+    max_attempts: u32,
+    reconnect_delay: Duration,
+    stream: Option<TcpStream>,
+    client: OnceLock<Client>,
+    batches: VecDeque<Batch>,
+    /// Latest slot stored in this session, by variable code.
+    last_slots: HashMap<String, DateTime<Utc>>,
+}
+
+/// Lines sent together, each ending with a newline.
+#[derive(Default)]
+struct Batch {
+    text: String,
+    lines: usize,
+}
+
+impl QuestDbWriter {
+    /// Writer to the QuestDB listening at `address`, as `host:port`. Connects lazily.
+    ///
+    /// The REST API is expected at `address` with [`IlpTransport::Http`], on port 9000 of the
+    /// same host otherwise, see [`QuestDbWriter::http_address`].
+    pub fn new(address: impl Into<String>, transport: IlpTransport) -> Self {
+        let address = address.into();
+        let http_address = match transport {
+            IlpTransport::Http => address.clone(),
+            IlpTransport::Tcp => {
+                let host = address.rsplit_once(':').map_or(&*address, |(host, _)| host);
+                format!("{host}:{DEFAULT_HTTP_PORT}")
+            }
+        };
+        Self {
+            address,
+            transport,
+            http_address,
+            stations_table: DEFAULT_STATIONS_TABLE.to_owned(),
+            timeseries_table: DEFAULT_TIMESERIES_TABLE.to_owned(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            reconnect_delay: DEFAULT_RECONNECT_DELAY,
+            stream: None,
+            client: OnceLock::new(),
+            batches: VecDeque::new(),
+            last_slots: HashMap::new(),
+        }
+    }
+
+    /// `host:port` of the REST API queried for [`SnapshotStore::last_slot`].
+    pub fn http_address(mut self, http_address: impl Into<String>) -> Self {
+        self.http_address = http_address.into();
+        self
+    }
+
+    pub fn stations_table(mut self, table: impl Into<String>) -> Self {
+        self.stations_table = table.into();
+        self
+    }
+
+    pub fn timeseries_table(mut self, table: impl Into<String>) -> Self {
+        self.timeseries_table = table.into();
+        self
+    }
+
+    /// Most lines sent in a single batch.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Attempts at sending a batch, reconnecting in between.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn reconnect_delay(mut self, reconnect_delay: Duration) -> Self {
+        self.reconnect_delay = reconnect_delay;
+        self
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Lines queued and not sent yet.
+    pub fn pending(&self) -> usize {
+        self.batches.iter().map(|batch| batch.lines).sum()
+    }
+
+    /// Queues the snapshot of the slot starting at `time`.
+    pub fn write_stations<T>(&mut self, time: DateTime<T>, stations: &Stations)
+    where
+        T: TimeZone,
+    {
+        let variable = stations.variable().code();
+        for station in stations.iter() {
+            let thresholds = station.thresholds();
+            let fields = [
+                ("value", station.value().copied()),
+                ("soglia1", thresholds.soglia1()),
+                ("soglia2", thresholds.soglia2()),
+                ("soglia3", thresholds.soglia3()),
+            ];
+            let mut line = Line::new(&self.stations_table);
+            line.symbol("station", station.idstazione().as_str())
+                .symbol("name", station.nomestaz())
+                .symbol("variable", variable);
+            for (name, value) in fields {
+                if let Some(value) = value {
+                    line.field(name, value);
+                }
+            }
+            self.push(line, &time);
+        }
+    }
+
+    /// Queues the readings of a time series of `station`, skipping the missing ones.
+    pub fn write_timeseries(&mut self, station: &StationId, series: &TimeSeries) {
+        let variable = series.variable().code();
+        for tv in series.iter() {
+            let Some(value) = tv.value() else {
+                continue;
+            };
+            let mut line = Line::new(&self.timeseries_table);
+            line.symbol("station", station.as_str())
+                .symbol("variable", variable)
+                .field("value", value);
+            self.push(line, &tv.time());
+        }
+    }
+
+    fn push<T>(&mut self, line: Line, time: &DateTime<T>)
+    where
+        T: TimeZone,
+    {
+        if !line.has_fields {
+            return;
+        }
+        if self
+            .batches
+            .back()
+            .is_none_or(|batch| batch.lines >= self.batch_size)
+        {
+            self.batches.push_back(Batch::default());
+        }
+        let batch = self.batches.back_mut().expect("pushed above");
+        // Out of range only past year 2262, far beyond any slot.
+        let nanos = time.timestamp_nanos_opt().unwrap_or(i64::MAX);
+        writeln!(batch.text, "{} {nanos}", line.text).expect("writing to a String can't fail");
+        batch.lines += 1;
+    }
+
+    /// Sends the queued batches in order, stopping at the first one that can't be sent.
+    fn send_batches(&mut self) -> Result<(), StorageError> {
+        while let Some(batch) = self.batches.pop_front() {
+            if let Err(error) = self.send_batch(&batch.text) {
+                self.batches.push_front(batch);
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends `text`, retrying on a new connection up to `max_attempts` times.
+    fn send_batch(&mut self, text: &str) -> Result<(), StorageError> {
+        let mut attempt = 1;
+        loop {
+            let result = match self.transport {
+                IlpTransport::Tcp => self.send_tcp(text),
+                IlpTransport::Http => self.send_http(text),
+            };
+            match result {
+                Ok(()) => return Ok(()),
+                Err(error) if attempt < self.max_attempts && is_transient(&error) => {
+                    warn!(
+                        "Couldn't write to QuestDB at {} (attempt {attempt}): {error}",
+                        self.address
+                    );
+                    self.stream = None;
+                    attempt += 1;
+                    thread::sleep(self.reconnect_delay);
+                }
+                Err(error) => {
+                    self.stream = None;
+                    return Err(error);
+                }
+            }
+        }
+    }
+
+    fn send_tcp(&mut self, text: &str) -> Result<(), StorageError> {
+        if self.stream.is_none() {
+            self.stream = Some(connect(&self.address)?);
+        }
+        let stream = self.stream.as_mut().expect("connected above");
+        stream
+            .write_all(text.as_bytes())
+            .and_then(|()| stream.flush())
+            .map_err(|source| StorageError::Network {
+                address: self.address.clone(),
+                source,
+            })
+    }
+
+    fn send_http(&self, text: &str) -> Result<(), StorageError> {
+        let response = self
+            .client()?
+            .post(format!("http://{}/write?precision=n", self.address))
+            .header(reqwest::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(text.to_owned())
+            .send()
+            .map_err(|source| http_error(&self.address, source))?;
+        check_status(response, &self.address).map(drop)
+    }
+
+    /// Latest timestamp of `variable` in the stations table, `None` if there is no row for it or
+    /// the table doesn't exist yet.
+    fn query_last_slot(&self, variable: &Variable) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let query = format!(
+            "SELECT max(timestamp) FROM \"{}\" WHERE variable = '{}'",
+            self.stations_table.replace('"', "\"\""),
+            variable.code().replace('\'', "''")
+        );
+        let response = self
+            .client()?
+            .get(format!("http://{}/exec", self.http_address))
+            .query(&[("query", query)])
+            .send()
+            .map_err(|source| http_error(&self.http_address, source))?;
+        let response = match check_status(response, &self.http_address) {
+            Ok(response) => response,
+            Err(StorageError::Rejected { message, .. }) if message.contains("does not exist") => {
+                return Ok(None);
+            }
+            Err(error) => return Err(error),
+        };
+        let body = response
+            .text()
+            .map_err(|source| http_error(&self.http_address, source))?;
+        let result = serde_json::from_str::<ExecResponse>(&body)
+            .map_err(|error| StorageError::Corrupt(format!("invalid QuestDB answer: {error}")))?;
+        Ok(result.dataset.into_iter().flatten().flatten().max())
+    }
+
+    /// HTTP client, created on first use so that it's built on the calling thread.
+    fn client(&self) -> Result<&Client, StorageError> {
+        if let Some(client) = self.client.get() {
+            return Ok(client);
+        }
+        let client = Client::builder()
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(|source| http_error(&self.address, source))?;
+        Ok(self.client.get_or_init(|| client))
+    }
+}
+
+impl SnapshotStore for QuestDbWriter {
+    fn store(&mut self, time: DateTime<Utc>, stations: &Stations) -> Result<(), StorageError> {
+        self.write_stations(time, stations);
+        let last = self
+            .last_slots
+            .entry(stations.variable().code().to_owned())
+            .or_insert(time);
+        *last = (*last).max(time);
+        Ok(())
+    }
+
+    fn last_slot(&self, variable: &Variable) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let stored = self.last_slots.get(variable.code()).copied();
+        Ok(self.query_last_slot(variable)?.max(stored))
+    }
+
+    fn flush(&mut self) -> Result<(), StorageError> {
+        self.send_batches()
+    }
+}
+
+impl Drop for QuestDbWriter {
+    fn drop(&mut self) {
+        let pending = self.pending();
+        if pending > 0 {
+            warn!("Dropped {pending} lines never sent to QuestDB");
+        }
+    }
+}
+
+/// Answer of `GET /exec`, the rows of the query.
+#[derive(Deserialize)]
+struct ExecResponse {
+    dataset: Vec<Vec<Option<DateTime<Utc>>>>,
+}
+
+/// Network errors and server errors are worth another attempt, rejected lines aren't.
+fn is_transient(error: &StorageError) -> bool {
+    match error {
+        StorageError::Network { .. } | StorageError::Http { .. } => true,
+        StorageError::Rejected { status, .. } => *status >= 500,
+        _ => false,
+    }
+}
+
+fn http_error(address: &str, source: reqwest::Error) -> StorageError {
+    StorageError::Http {
+        address: address.to_owned(),
+        source,
+    }
+}
+
+/// Turns a non success status into [`StorageError::Rejected`] with the body as message.
+fn check_status(
+    response: reqwest::blocking::Response,
+    address: &str,
+) -> Result<reqwest::blocking::Response, StorageError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response
+        .text()
+        .map_err(|source| http_error(address, source))?;
+    Err(StorageError::Rejected {
+        status: status.as_u16(),
+        message: message.trim().to_owned(),
+    })
+}
+
+fn connect(address: &str) -> Result<TcpStream, StorageError> {
+    let network_error = |source| StorageError::Network {
+        address: address.to_owned(),
+        source,
+    };
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "address didn't resolve");
+    for addr in address.to_socket_addrs().map_err(network_error)? {
+        match TcpStream::connect_timeout(&addr, TIMEOUT) {
+            Ok(stream) => {
+                stream
+                    .set_write_timeout(Some(TIMEOUT))
+                    .and_then(|()| stream.set_nodelay(true))
+                    .map_err(network_error)?;
+                return Ok(stream);
+            }
+            Err(error) => last_error = error,
+        }
+    }
+    Err(network_error(last_error))
+}
+
+/// A line without its timestamp.
+struct Line {
+    text: String,
+    has_fields: bool,
+}
+
+impl Line {
+    fn new(table: &str) -> Self {
+        let mut text = String::new();
+        escape_into(&mut text, table, &[',', ' ']);
+        Self {
+            text,
+            has_fields: false,
+        }
+    }
+
+    fn symbol(&mut self, name: &str, value: &str) -> &mut Self {
+        debug_assert!(!self.has_fields, "symbols go before fields");
+        self.text.push(',');
+        escape_into(&mut self.text, name, &[',', ' ', '=']);
+        self.text.push('=');
+        escape_into(&mut self.text, value, &[',', ' ', '=']);
+        self
+    }
+
+    /// Adds a float field, non finite values are left out.
+    fn field<F>(&mut self, name: &str, value: F) -> &mut Self
+    where
+        F: Into<f64> + fmt::Debug + Copy,
+    {
+        if !value.into().is_finite() {
+            return self;
+        }
+        self.text.push(if self.has_fields { ',' } else { ' ' });
+        escape_into(&mut self.text, name, &[',', ' ', '=']);
+        // `{:?}` keeps the decimal point, so QuestDB doesn't take the value for an integer.
+        write!(self.text, "={value:?}").expect("writing to a String can't fail");
+        self.has_fields = true;
+        self
+    }
+}
+
+/// Backslash-escapes `special` characters; newlines can't be escaped in ILP and become spaces.
+fn escape_into(out: &mut String, value: &str, special: &[char]) {
+    for c in value.chars() {
+        let c = if matches!(c, '\n' | '\r') { ' ' } else { c };
+        if special.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead as _, BufReader, Read as _},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    use super::*;
+    use crate::model::{
+        Station, Thresholds,
+        fixtures::{ID, info, stations},
+    };
+
+    const LINE: &str = "stations,station=-/1\\,2/simnbo,name=Test,variable=254\\,0\\,0/1\\,-\\,-\\,-/B13215 \
+        value=1.5,soglia1=1.0 1714555800000000000\n";
+
+    fn slot() -> DateTime<Utc> {
+        "2024-05-01T09:30:00Z".parse().unwrap()
+    }
+
+    /// Local HTTP server answering each request with the next of `replies`, as status and body,
+    /// then reporting the request line and body it got.
+    fn http_server(
+        replies: Vec<(u16, &'static str)>,
+    ) -> (String, mpsc::Receiver<(String, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for (status, body) in replies {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut length = 0;
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    if let Some(value) = header.to_ascii_lowercase().strip_prefix("content-length:")
+                    {
+                        length = value.trim().parse().unwrap();
+                    }
+                    header.clear();
+                }
+                let mut content = vec![0; length];
+                reader.read_exact(&mut content).unwrap();
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {status} Status\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+                let content = String::from_utf8(content).unwrap();
+                sender
+                    .send((request.trim_end().to_owned(), content))
+                    .unwrap();
+            }
+        });
+        (address, receiver)
+    }
+
+    fn http_writer(address: &str) -> QuestDbWriter {
+        QuestDbWriter::new(address, IlpTransport::Http).reconnect_delay(Duration::ZERO)
+    }
+
+    #[test]
+    fn escapes_names_and_values() {
+        let mut line = Line::new("my table,1");
+        line.symbol("the name", "a=b,c d\ne")
+            .field("va=l", 2.0_f32)
+            .field("nan", f64::NAN)
+            .field("inf", f32::NEG_INFINITY)
+            .field("other", 0.1_f64);
+
+        assert_eq!(
+            line.text,
+            "my\\ table\\,1,the\\ name=a\\=b\\,c\\ d\\ e va\\=l=2.0,other=0.1"
+        );
+        assert!(line.has_fields);
+    }
+
+    #[test]
+    fn lines_without_fields_are_left_out() {
+        let mut line = Line::new("t");
+        line.symbol("s", "v").field("value", f64::NAN);
+        assert!(!line.has_fields);
+
+        let mut writer = QuestDbWriter::new("localhost:9009", IlpTransport::Tcp);
+        let info = info(ID, 1, Thresholds::default());
+        let stations = Stations::new(vec![Station::from_parts(info, None)]);
+        writer.store(slot(), &stations).unwrap();
+        assert_eq!(writer.pending(), 0);
+    }
+
+    #[test]
+    fn defaults_the_rest_api_to_port_9000() {
+        let tcp = QuestDbWriter::new("db.local:9009", IlpTransport::Tcp);
+        let http = QuestDbWriter::new("db.local:9100", IlpTransport::Http);
+        assert_eq!(tcp.http_address, "db.local:9000");
+        assert_eq!(http.http_address, "db.local:9100");
+    }
+
+    #[test]
+    fn sends_lines_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let mut received = String::new();
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.read_to_string(&mut received).unwrap();
+            received
+        });
+
+        let mut writer = QuestDbWriter::new(&address, IlpTransport::Tcp);
+        writer.store(slot(), &stations(&[Some(1.5)])).unwrap();
+        assert_eq!(writer.pending(), 1);
+        writer.flush().unwrap();
+        assert_eq!(writer.pending(), 0);
+        drop(writer);
+
+        assert_eq!(server.join().unwrap(), LINE);
+    }
+
+    #[test]
+    fn keeps_the_lines_it_couldnt_send() {
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+
+        let mut writer = QuestDbWriter::new(address, IlpTransport::Tcp).max_attempts(1);
+        writer.store(slot(), &stations(&[Some(1.5)])).unwrap();
+
+        assert!(matches!(writer.flush(), Err(StorageError::Network { .. })));
+        assert_eq!(writer.pending(), 1);
+    }
+
+    #[test]
+    fn retries_server_errors_over_http() {
+        let (address, requests) = http_server(vec![(500, "busy"), (204, "")]);
+
+        let mut writer = http_writer(&address);
+        writer.store(slot(), &stations(&[Some(1.5)])).unwrap();
+        writer.flush().unwrap();
+
+        for _ in 0..2 {
+            let (request, body) = requests.recv().unwrap();
+            assert_eq!(request, "POST /write?precision=n HTTP/1.1");
+            assert_eq!(body, LINE);
+        }
+        assert_eq!(writer.pending(), 0);
+    }
+
+    #[test]
+    fn rejected_batches_wait_for_the_next_flush() {
+        let (address, requests) = http_server(vec![(400, "bad line"), (204, "")]);
+
+        let mut writer = http_writer(&address);
+        writer.store(slot(), &stations(&[Some(1.5)])).unwrap();
+
+        let Err(StorageError::Rejected { status, message }) = writer.flush() else {
+            panic!("expected the batch to be rejected");
+        };
+        assert_eq!((status, message.as_str()), (400, "bad line"));
+        assert_eq!(writer.pending(), 1);
+
+        writer.flush().unwrap();
+        assert_eq!(requests.iter().take(2).count(), 2);
+        assert_eq!(writer.pending(), 0);
+    }
+
+    #[test]
+    fn splits_batches() {
+        let (address, requests) = http_server(vec![(204, ""), (204, "")]);
+
+        let mut writer = http_writer(&address).batch_size(1);
+        writer
+            .store(slot(), &stations(&[Some(1.5), Some(2.5)]))
+            .unwrap();
+        writer.flush().unwrap();
+
+        let bodies = requests
+            .iter()
+            .take(2)
+            .map(|(_, body)| body)
+            .collect::<Vec<_>>();
+        assert!(bodies.iter().all(|body| body.lines().count() == 1));
+        assert!(bodies[1].contains("value=2.5"));
+    }
+
+    #[test]
+    fn asks_the_rest_api_for_the_last_slot() {
+        let (address, requests) = http_server(vec![
+            (
+                200,
+                r#"{"dataset": [["2024-05-01T09:30:00.000000Z"]], "count": 1}"#,
+            ),
+            (200, r#"{"dataset": [[null]], "count": 1}"#),
+            (400, r#"{"error": "table does not exist [table=stations]"}"#),
+            (400, r#"{"error": "table does not exist [table=stations]"}"#),
+        ]);
+
+        let mut writer = http_writer(&address);
+        assert_eq!(
+            writer.last_slot(&Variable::HYDROMETRIC_LEVEL).unwrap(),
+            Some(slot())
+        );
+        let (request, _) = requests.recv().unwrap();
+        assert!(request.starts_with("GET /exec?query=SELECT+max"));
+        assert!(request.contains("WHERE+variable+%3D+%27254%2C0%2C0%2F1%2C-%2C-%2C-%2FB13215%27"));
+
+        assert_eq!(
+            writer.last_slot(&Variable::HYDROMETRIC_LEVEL).unwrap(),
+            None
+        );
+        writer.store(slot(), &stations(&[Some(1.5)])).unwrap();
+        assert_eq!(
+            writer.last_slot(&Variable::HYDROMETRIC_LEVEL).unwrap(),
+            Some(slot())
+        );
+        assert_eq!(writer.last_slot(&Variable::PRECIPITATION).unwrap(), None);
+    }
+}
@@ -0,0 +1,728 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use rusqlite::{Connection, OptionalExtension as _, Row, params};
+
+use crate::{
+    api::{Clock, StationsError, SystemClock, Variable},
+    model::{
+        Reading, Station, StationId, StationInfo, StationRegistry, Stations, StationsResponse,
+        Thresholds, TimeSeries, TimeValue,
+    },
+    source::{BoxFuture, StationSource, run_blocking},
+    storage::{SnapshotStore, StorageError},
+};
+
+/// Span of the series served as a [`StationSource`], about what upstream returns.
+const SERIES_SPAN: TimeDelta = TimeDelta::hours(72);
+
+/// Times are stored as Unix milliseconds, like upstream sends them; station metadata and
+/// readings are kept per variable, as each one has its own set of stations and thresholds.
+/// Values and thresholds are stored as the decimal upstream sent, see [`decimal`].
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS stations (
+    id TEXT NOT NULL,
+    variable TEXT NOT NULL,
+    ordinamento INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    lon TEXT NOT NULL,
+    lat TEXT NOT NULL,
+    soglia1 REAL,
+    soglia2 REAL,
+    soglia3 REAL,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (id, variable)
+);
+CREATE TABLE IF NOT EXISTS readings (
+    station TEXT NOT NULL,
+    variable TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    value REAL,
+    PRIMARY KEY (station, variable, time)
+) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS readings_by_time ON readings (variable, time);
+CREATE TABLE IF NOT EXISTS threshold_history (
+    station TEXT NOT NULL,
+    variable TEXT NOT NULL,
+    valid_from INTEGER NOT NULL,
+    soglia1 REAL,
+    soglia2 REAL,
+    soglia3 REAL,
+    PRIMARY KEY (station, variable, valid_from)
+);
+CREATE TABLE IF NOT EXISTS scrapes (
+    variable TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    scraped_at INTEGER NOT NULL,
+    stations INTEGER NOT NULL,
+    with_value INTEGER NOT NULL,
+    PRIMARY KEY (variable, slot)
+);
+";
+
+/// Archive of snapshots and time series in a SQLite database.
+///
+/// Every write is an upsert, so storing the same snapshot or series again is harmless. Station
+/// metadata follows the most recent snapshot, while threshold changes are kept in their own
+/// history and applied when rebuilding older snapshots.
+///
+/// Clones share the same connection.
+#[derive(Clone)]
+pub struct SqliteStore {
+    connection: Arc<Mutex<Connection>>,
+    clock: Arc<dyn Clock>,
+}
+
+/// A stored snapshot, see [`SqliteStore::scrapes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scrape {
+    slot: DateTime<Utc>,
+    scraped_at: DateTime<Utc>,
+    stations: usize,
+    with_value: usize,
+}
+
+impl Scrape {
+    pub fn slot(&self) -> DateTime<Utc> {
+        self.slot
+    }
+
+    /// When the snapshot was last stored.
+    pub fn scraped_at(&self) -> DateTime<Utc> {
+        self.scraped_at
+    }
+
+    /// Number of stations in the snapshot.
+    pub fn stations(&self) -> usize {
+        self.stations
+    }
+
+    /// Number of stations in the snapshot with a reading.
+    pub fn with_value(&self) -> usize {
+        self.with_value
+    }
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it and its tables if missing.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(connection: Connection) -> Result<Self, StorageError> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Clock the time a snapshot was stored at is read from, see [`Scrape::scraped_at`].
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Stores the snapshot of the slot starting at `time`, for the variable of `stations`.
+    pub fn upsert_stations<T>(
+        &self,
+        time: DateTime<T>,
+        stations: &Stations,
+    ) -> Result<(), StorageError>
+    where
+        T: TimeZone,
+    {
+        let slot = time.timestamp_millis();
+        let variable = stations.variable().code();
+
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        {
+            let mut upsert_station = transaction.prepare_cached(
+                "INSERT INTO stations
+                    (id, variable, ordinamento, name, lon, lat, soglia1, soglia2, soglia3, updated_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                ON CONFLICT (id, variable) DO UPDATE SET
+                    ordinamento = excluded.ordinamento,
+                    name = excluded.name,
+                    lon = excluded.lon,
+                    lat = excluded.lat,
+                    soglia1 = excluded.soglia1,
+                    soglia2 = excluded.soglia2,
+                    soglia3 = excluded.soglia3,
+                    updated_at = excluded.updated_at
+                WHERE excluded.updated_at >= stations.updated_at",
+            )?;
+            let mut upsert_reading = transaction.prepare_cached(
+                "INSERT INTO readings (station, variable, time, value) VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (station, variable, time) DO UPDATE SET value = excluded.value",
+            )?;
+            let mut thresholds_in_effect = transaction.prepare_cached(
+                "SELECT soglia1, soglia2, soglia3 FROM threshold_history
+                WHERE station = ?1 AND variable = ?2 AND valid_from <= ?3
+                ORDER BY valid_from DESC LIMIT 1",
+            )?;
+            let mut insert_thresholds = transaction.prepare_cached(
+                "INSERT OR REPLACE INTO threshold_history
+                    (station, variable, valid_from, soglia1, soglia2, soglia3)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+
+            for station in stations.iter() {
+                let info = station.info();
+                let id = info.idstazione().as_str();
+                let thresholds = info.thresholds();
+
+                upsert_station.execute(params![
+                    id,
+                    variable,
+                    info.ordinamento() as i64,
+                    info.nomestaz(),
+                    info.lon(),
+                    info.lat(),
+                    thresholds.soglia1().map(decimal),
+                    thresholds.soglia2().map(decimal),
+                    thresholds.soglia3().map(decimal),
+                    slot,
+                ])?;
+                let value = station.value().copied().map(decimal);
+                upsert_reading.execute(params![id, variable, slot, value])?;
+
+                let in_effect = thresholds_in_effect
+                    .query_row(params![id, variable, slot], |row| read_thresholds(row, 0))
+                    .optional()?;
+                if in_effect.as_ref() != Some(thresholds) {
+                    insert_thresholds.execute(params![
+                        id,
+                        variable,
+                        slot,
+                        thresholds.soglia1().map(decimal),
+                        thresholds.soglia2().map(decimal),
+                        thresholds.soglia3().map(decimal),
+                    ])?;
+                }
+            }
+        }
+
+        let with_value = stations
+            .iter()
+            .filter(|station| station.value().is_some())
+            .count();
+        transaction.execute(
+            "INSERT INTO scrapes (variable, slot, scraped_at, stations, with_value)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT (variable, slot) DO UPDATE SET
+                scraped_at = excluded.scraped_at,
+                stations = excluded.stations,
+                with_value = excluded.with_value",
+            params![
+                variable,
+                slot,
+                self.clock.now().timestamp_millis(),
+                stations.len() as i64,
+                with_value as i64,
+            ],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Stores the readings of a time series of `station`, for the variable of `series`.
+    ///
+    /// Returns the number of readings written.
+    pub fn upsert_timeseries(
+        &self,
+        station: &StationId,
+        series: &TimeSeries,
+    ) -> Result<usize, StorageError> {
+        let variable = series.variable().code();
+
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        {
+            let mut upsert_reading = transaction.prepare_cached(
+                "INSERT INTO readings (station, variable, time, value) VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (station, variable, time) DO UPDATE SET value = excluded.value",
+            )?;
+            for tv in series.iter() {
+                upsert_reading.execute(params![
+                    station.as_str(),
+                    variable,
+                    tv.time().timestamp_millis(),
+                    tv.value(),
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(series.len())
+    }
+
+    /// Readings of `station` from `start` to `end`, both included, in chronological order.
+    pub fn readings<T>(
+        &self,
+        variable: &Variable,
+        station: &StationId,
+        start: DateTime<T>,
+        end: DateTime<T>,
+    ) -> Result<Vec<Reading>, StorageError>
+    where
+        T: TimeZone,
+    {
+        self.query_readings(variable, station, start.to_utc(), end.to_utc())?
+            .into_iter()
+            .map(|(time, value)| {
+                Ok(Reading::new(
+                    station.clone(),
+                    from_millis(time)?,
+                    value.map(|value| value as f32),
+                ))
+            })
+            .collect()
+    }
+
+    /// Time series of `station` from `start` to `end`, both included.
+    pub fn timeseries<T>(
+        &self,
+        variable: &Variable,
+        station: &StationId,
+        start: DateTime<T>,
+        end: DateTime<T>,
+    ) -> Result<TimeSeries, StorageError>
+    where
+        T: TimeZone,
+    {
+        let values = self
+            .query_readings(variable, station, start.to_utc(), end.to_utc())?
+            .into_iter()
+            .map(|(time, value)| {
+                TimeValue::new(from_millis(time)?, value)
+                    .map_err(|error| StorageError::Corrupt(error.to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(TimeSeries::new(values).with_variable(variable.clone()))
+    }
+
+    fn query_readings(
+        &self,
+        variable: &Variable,
+        station: &StationId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(i64, Option<f64>)>, StorageError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(
+            "SELECT time, value FROM readings
+            WHERE station = ?1 AND variable = ?2 AND time BETWEEN ?3 AND ?4
+            ORDER BY time",
+        )?;
+        let rows = statement
+            .query_map(
+                params![
+                    station.as_str(),
+                    variable.code(),
+                    start.timestamp_millis(),
+                    end.timestamp_millis(),
+                ],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?
+            .collect::<Result<_, _>>()?;
+        Ok(rows)
+    }
+
+    /// Rebuilds the snapshot of the slot starting at `time` with the thresholds in effect back
+    /// then, empty if nothing was stored for it.
+    pub fn snapshot<T>(
+        &self,
+        variable: &Variable,
+        time: DateTime<T>,
+    ) -> Result<Stations, StorageError>
+    where
+        T: TimeZone,
+    {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(
+            "SELECT s.id, s.ordinamento, s.name, s.lon, s.lat,
+                COALESCE(h.soglia1, CASE WHEN h.station IS NULL THEN s.soglia1 END),
+                COALESCE(h.soglia2, CASE WHEN h.station IS NULL THEN s.soglia2 END),
+                COALESCE(h.soglia3, CASE WHEN h.station IS NULL THEN s.soglia3 END),
+                r.value
+            FROM readings r
+            JOIN stations s ON s.id = r.station AND s.variable = r.variable
+            LEFT JOIN threshold_history h ON h.station = r.station AND h.variable = r.variable
+                AND h.valid_from = (
+                    SELECT MAX(valid_from) FROM threshold_history
+                    WHERE station = r.station AND variable = r.variable AND valid_from <= r.time
+                )
+            WHERE r.variable = ?1 AND r.time = ?2
+            ORDER BY s.ordinamento, s.id",
+        )?;
+        let rows = statement
+            .query_map(params![variable.code(), time.timestamp_millis()], |row| {
+                Ok((read_info(row)?, row.get::<_, Option<f32>>(8)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let stations = rows
+            .into_iter()
+            .map(|(info, value)| Ok(Station::from_parts(info.build()?, value)))
+            .collect::<Result<_, StorageError>>()?;
+        Ok(Stations::new(stations).with_variable(variable.clone()))
+    }
+
+    /// Current metadata of the stations of `variable`.
+    pub fn registry(&self, variable: &Variable) -> Result<StationRegistry, StorageError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(
+            "SELECT id, ordinamento, name, lon, lat, soglia1, soglia2, soglia3
+            FROM stations WHERE variable = ?1",
+        )?;
+        let rows = statement
+            .query_map(params![variable.code()], read_info)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut registry = StationRegistry::new();
+        for info in rows {
+            registry.insert(info.build()?);
+        }
+        Ok(registry)
+    }
+
+    /// Thresholds of `station` over time, each with the slot it was first seen in.
+    pub fn threshold_history(
+        &self,
+        variable: &Variable,
+        station: &StationId,
+    ) -> Result<Vec<(DateTime<Utc>, Thresholds)>, StorageError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(
+            "SELECT valid_from, soglia1, soglia2, soglia3 FROM threshold_history
+            WHERE station = ?1 AND variable = ?2 ORDER BY valid_from",
+        )?;
+        let rows = statement
+            .query_map(params![station.as_str(), variable.code()], |row| {
+                Ok((row.get::<_, i64>(0)?, read_thresholds(row, 1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(valid_from, thresholds)| Ok((from_millis(valid_from)?, thresholds)))
+            .collect()
+    }
+
+    /// Snapshots stored for `variable` from `start` to `end`, both included.
+    pub fn scrapes<T>(
+        &self,
+        variable: &Variable,
+        start: DateTime<T>,
+        end: DateTime<T>,
+    ) -> Result<Vec<Scrape>, StorageError>
+    where
+        T: TimeZone,
+    {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(
+            "SELECT slot, scraped_at, stations, with_value FROM scrapes
+            WHERE variable = ?1 AND slot BETWEEN ?2 AND ?3 ORDER BY slot",
+        )?;
+        let rows = statement
+            .query_map(
+                params![
+                    variable.code(),
+                    start.timestamp_millis(),
+                    end.timestamp_millis()
+                ],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, i64>(3)?,
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(slot, scraped_at, stations, with_value)| {
+                Ok(Scrape {
+                    slot: from_millis(slot)?,
+                    scraped_at: from_millis(scraped_at)?,
+                    stations: stations as usize,
+                    with_value: with_value as usize,
+                })
+            })
+            .collect()
+    }
+
+    /// Time of the most recent reading of `station`.
+    fn last_reading(
+        &self,
+        variable: &Variable,
+        station: &StationId,
+    ) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let last = self.connection().query_row(
+            "SELECT MAX(time) FROM readings WHERE station = ?1 AND variable = ?2",
+            params![station.as_str(), variable.code()],
+            |row| row.get::<_, Option<i64>>(0),
+        )?;
+        last.map(from_millis).transpose()
+    }
+}
+
+impl SnapshotStore for SqliteStore {
+    fn store(&mut self, time: DateTime<Utc>, stations: &Stations) -> Result<(), StorageError> {
+        self.upsert_stations(time, stations)
+    }
+
+    fn last_slot(&self, variable: &Variable) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let last = self.connection().query_row(
+            "SELECT MAX(slot) FROM scrapes WHERE variable = ?1",
+            params![variable.code()],
+            |row| row.get::<_, Option<i64>>(0),
+        )?;
+        last.map(from_millis).transpose()
+    }
+}
+
+/// Serves the archive like upstream: time series cover the last [`SERIES_SPAN`] stored for the
+/// station, and a slot that was never stored is an empty snapshot. Queries run on the blocking
+/// thread pool.
+impl StationSource for SqliteStore {
+    fn stations_at<'a>(
+        &'a self,
+        variable: &'a Variable,
+        time: DateTime<Utc>,
+    ) -> BoxFuture<'a, Result<StationsResponse, StationsError>> {
+        let store = self.clone();
+        let variable = variable.clone();
+        Box::pin(run_blocking(move || {
+            let stations = store.snapshot(&variable, time)?;
+            Ok(StationsResponse::new(stations, Vec::new()))
+        }))
+    }
+
+    fn station_timeseries<'a>(
+        &'a self,
+        variable: &'a Variable,
+        station_id: &'a StationId,
+    ) -> BoxFuture<'a, Result<TimeSeries, StationsError>> {
+        let store = self.clone();
+        let variable = variable.clone();
+        let station_id = station_id.clone();
+        Box::pin(run_blocking(move || {
+            let series = match store.last_reading(&variable, &station_id)? {
+                Some(end) => store.timeseries(&variable, &station_id, end - SERIES_SPAN, end)?,
+                None => TimeSeries::new(Vec::new()).with_variable(variable),
+            };
+            Ok(series)
+        }))
+    }
+}
+
+/// Columns of a [`StationInfo`], the id still to be parsed.
+struct InfoRow {
+    id: String,
+    ordinamento: i64,
+    name: String,
+    lon: String,
+    lat: String,
+    thresholds: Thresholds,
+}
+
+impl InfoRow {
+    fn build(self) -> Result<StationInfo, StorageError> {
+        let id = self
+            .id
+            .parse::<StationId>()
+            .map_err(|error| StorageError::Corrupt(error.to_string()))?;
+        Ok(StationInfo::new(
+            id,
+            self.ordinamento as usize,
+            self.name,
+            self.lon,
+            self.lat,
+            self.thresholds,
+        ))
+    }
+}
+
+/// Reads `id, ordinamento, name, lon, lat, soglia1, soglia2, soglia3`.
+fn read_info(row: &Row<'_>) -> rusqlite::Result<InfoRow> {
+    Ok(InfoRow {
+        id: row.get(0)?,
+        ordinamento: row.get(1)?,
+        name: row.get(2)?,
+        lon: row.get(3)?,
+        lat: row.get(4)?,
+        thresholds: read_thresholds(row, 5)?,
+    })
+}
+
+/// Reads `soglia1, soglia2, soglia3` starting at column `first`.
+fn read_thresholds(row: &Row<'_>, first: usize) -> rusqlite::Result<Thresholds> {
+    Ok(Thresholds::new(
+        row.get(first)?,
+        row.get(first + 1)?,
+        row.get(first + 2)?,
+    ))
+}
+
+/// The `f64` closest to the shortest decimal that reads back as `value`.
+///
+/// Snapshots carry `f32` values and time series `f64` ones: widening `1.23_f32` would store
+/// `1.2300000190734863`, which an upsert from a series would then overwrite with `1.23` and
+/// the other way round. Going through the decimal stores the same reading the same way.
+fn decimal(value: f32) -> f64 {
+    value.to_string().parse().unwrap_or(f64::from(value))
+}
+
+fn from_millis(millis: i64) -> Result<DateTime<Utc>, StorageError> {
+    DateTime::from_timestamp_millis(millis)
+        .ok_or_else(|| StorageError::Corrupt(format!("timestamp out of range: {millis}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::FixedClock,
+        model::fixtures::{ID, station},
+    };
+
+    fn slot(minutes: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap() + TimeDelta::minutes(minutes)
+    }
+
+    fn stored_values(store: &SqliteStore) -> Vec<Option<f64>> {
+        let connection = store.connection();
+        let mut statement = connection
+            .prepare("SELECT value FROM readings ORDER BY time")
+            .unwrap();
+        statement
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn snapshot_and_series_values_are_stored_alike() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let id = ID.parse::<StationId>().unwrap();
+        let series = TimeSeries::new(vec![TimeValue::new(slot(0), Some(1.23)).unwrap()]);
+
+        store
+            .upsert_stations(slot(0), &Stations::new(vec![station(ID, Some(1.23), 2.0)]))
+            .unwrap();
+        assert_eq!(stored_values(&store), [Some(1.23)]);
+        store.upsert_timeseries(&id, &series).unwrap();
+        assert_eq!(stored_values(&store), [Some(1.23)]);
+
+        let readings = store
+            .readings(&Variable::default(), &id, slot(0), slot(0))
+            .unwrap();
+        assert_eq!(readings[0].value(), Some(1.23_f32));
+    }
+
+    #[test]
+    fn snapshots_are_rebuilt_with_the_thresholds_of_their_time() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        let variable = Variable::default();
+
+        store
+            .store(slot(0), &Stations::new(vec![station(ID, Some(1.5), 1.0)]))
+            .unwrap();
+        store
+            .store(slot(15), &Stations::new(vec![station(ID, None, 2.0)]))
+            .unwrap();
+        store
+            .store(slot(30), &Stations::new(vec![station(ID, Some(2.5), 2.0)]))
+            .unwrap();
+
+        let first = store.snapshot(&variable, slot(0)).unwrap();
+        let first = first.iter().next().unwrap();
+        assert_eq!((first.value(), first.soglia1()), (Some(&1.5), Some(1.0)));
+        assert!(store.snapshot(&variable, slot(45)).unwrap().is_empty());
+
+        let id = ID.parse::<StationId>().unwrap();
+        let history = store.threshold_history(&variable, &id).unwrap();
+        let history = history
+            .iter()
+            .map(|(time, thresholds)| (*time, thresholds.soglia1()))
+            .collect::<Vec<_>>();
+        assert_eq!(history, [(slot(0), Some(1.0)), (slot(15), Some(2.0))]);
+        assert_eq!(
+            store
+                .registry(&variable)
+                .unwrap()
+                .get(&id)
+                .map(|info| info.thresholds().soglia1()),
+            Some(Some(2.0))
+        );
+    }
+
+    #[test]
+    fn scrapes_are_recorded_once_per_slot() {
+        let now = slot(100);
+        let mut store = SqliteStore::open_in_memory()
+            .unwrap()
+            .clock(Arc::new(FixedClock::new(now)));
+        let level = Variable::HYDROMETRIC_LEVEL;
+        let rain = Variable::PRECIPITATION;
+        assert_eq!(store.last_slot(&level).unwrap(), None);
+
+        let stations = Stations::new(vec![station(ID, Some(1.0), 1.0)]);
+        store.store(slot(15), &stations).unwrap();
+        store.store(slot(0), &stations).unwrap();
+        store.store(slot(15), &stations).unwrap();
+        store
+            .store(slot(30), &stations.clone().with_variable(rain.clone()))
+            .unwrap();
+
+        let scrapes = store.scrapes(&level, slot(0), slot(60)).unwrap();
+        let slots = scrapes.iter().map(Scrape::slot).collect::<Vec<_>>();
+        assert_eq!(slots, [slot(0), slot(15)]);
+        assert_eq!((scrapes[0].stations(), scrapes[0].with_value()), (1, 1));
+        assert_eq!(scrapes[0].scraped_at(), now);
+        assert_eq!(store.last_slot(&level).unwrap(), Some(slot(15)));
+        assert_eq!(store.last_slot(&rain).unwrap(), Some(slot(30)));
+    }
+
+    #[tokio::test]
+    async fn serves_the_recent_series_as_a_source() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let id = ID.parse::<StationId>().unwrap();
+        let values = [0, 60 * 70, 60 * 80]
+            .map(|minutes| TimeValue::new(slot(minutes), Some(1.0)).unwrap())
+            .to_vec();
+        store
+            .upsert_timeseries(&id, &TimeSeries::new(values))
+            .unwrap();
+
+        let series = store
+            .station_timeseries(&Variable::default(), &id)
+            .await
+            .unwrap();
+        let unknown = "-/9,9/simnbo".parse::<StationId>().unwrap();
+        let empty = store
+            .station_timeseries(&Variable::default(), &unknown)
+            .await
+            .unwrap();
+
+        assert_eq!(series.len(), 2);
+        assert!(empty.is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::scraper::Scraper;
+use alert_core::{
+    api::{AlertClient, DELTA_15MIN, Variable},
+    storage::{IlpTransport, JsonDirStore, QuestDbWriter, SnapshotStore, SqliteStore},
+};
+use argh::FromArgs;
+use chrono::TimeDelta;
+use std::path::PathBuf;
+
+#[derive(FromArgs, Debug, Clone)]
+#[argh(description = "Alert scraper: archives every stations snapshot")]
+pub struct Args {
+    #[argh(
+        option,
+        short = 'o',
+        description = "directory the snapshots are stored in, as <variable>/stations_<unix millis>.json"
+    )]
+    pub output: Option<PathBuf>,
+    #[argh(
+        option,
+        description = "SQLite database the snapshots are stored in, instead of --output"
+    )]
+    pub sqlite: Option<PathBuf>,
+    #[argh(
+        option,
+        description = "host:port of a QuestDB the snapshots are sent to over ILP, instead of --output"
+    )]
+    pub questdb: Option<String>,
+    #[argh(
+        switch,
+        description = "send to QuestDB with ILP over HTTP (port 9000) instead of TCP (port 9009)"
+    )]
+    pub questdb_http: bool,
+    #[argh(
+        option,
+        description = "host:port of the QuestDB REST API, asked for the last stored slot (default port 9000 of --questdb)"
+    )]
+    pub questdb_rest: Option<String>,
+    #[argh(
+        option,
+        default = "String::from(\"stations\")",
+        description = "table the snapshots are written to in QuestDB (default stations)"
+    )]
+    pub questdb_table: String,
+    #[argh(
+        option,
+        default = "15",
+        description = "minutes between two scraped slots, a multiple of 15 (default 15)"
+    )]
+    pub interval: u32,
+    #[argh(
+        option,
+        default = "15",
+        description = "minutes to wait after a slot starts before fetching it (default 15)"
+    )]
+    pub delay: u32,
+    #[argh(
+        option,
+        default = "0",
+        description = "hours of history to fetch when the store is empty (default 0)"
+    )]
+    pub backfill: u32,
+    #[argh(
+        option,
+        default = "48",
+        description = "hours of missed slots fetched when resuming after downtime (default 48)"
+    )]
+    pub max_catch_up: u32,
+    #[argh(
+        option,
+        description = "upstream code of the variable to scrape (default hydrometric level)"
+    )]
+    pub variable: Option<String>,
+    #[argh(option, description = "base URL of the allerta API")]
+    pub base_url: Option<String>,
+    #[argh(switch, description = "catch up to the latest slot and exit")]
+    pub once: bool,
+}
+
+pub async fn run_scraper(args: Args) -> anyhow::Result<()> {
+    let interval = TimeDelta::minutes(i64::from(args.interval));
+    if interval <= TimeDelta::zero() || interval.num_seconds() % DELTA_15MIN.num_seconds() != 0 {
+        anyhow::bail!("--interval must be a positive multiple of 15 minutes");
+    }
+    let variable = match args.variable {
+        Some(code) => Variable::from_code(&code)
+            .ok_or_else(|| anyhow::anyhow!("--variable: unknown variable code {code:?}"))?,
+        None => Variable::default(),
+    };
+
+    let mut client = AlertClient::builder();
+    if let Some(base_url) = args.base_url {
+        client = client.base_url(base_url);
+    }
+    let client = client.build()?;
+    let store: Box<dyn SnapshotStore + Send> = match (args.output, args.sqlite, args.questdb) {
+        (Some(dir), None, None) => Box::new(JsonDirStore::new(dir)?),
+        (None, Some(path), None) => {
+            Box::new(SqliteStore::open(path)?.clock(client.clock().clone()))
+        }
+        (None, None, Some(address)) => {
+            let transport = if args.questdb_http {
+                IlpTransport::Http
+            } else {
+                IlpTransport::Tcp
+            };
+            let mut writer =
+                QuestDbWriter::new(address, transport).stations_table(args.questdb_table);
+            if let Some(rest) = args.questdb_rest {
+                writer = writer.http_address(rest);
+            }
+            Box::new(writer)
+        }
+        _ => anyhow::bail!("exactly one of --output, --sqlite and --questdb is required"),
+    };
+
+    Scraper::new(client, store, variable)
+        .interval(interval)
+        .delay(TimeDelta::minutes(i64::from(args.delay)))
+        .max_catch_up(TimeDelta::hours(i64::from(args.max_catch_up)))
+        .run(TimeDelta::hours(i64::from(args.backfill)), args.once)
+        .await
+}
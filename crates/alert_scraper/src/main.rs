@@ -0,0 +1,9 @@
+mod cli;
+mod scraper;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args: cli::Args = argh::from_env();
+    cli::run_scraper(args).await
+}
@@ -0,0 +1,342 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use alert_core::{
+    api::{AlertClient, DELTA_15MIN, Variable, clamp_station_time},
+    model::Stations,
+    storage::{SnapshotStore, StorageError},
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use futures_util::StreamExt as _;
+use log::{info, warn};
+
+/// How long after a slot starts upstream is expected to have published it.
+const DEFAULT_DELAY: TimeDelta = TimeDelta::minutes(15);
+/// How far back a restart catches up, upstream doesn't keep snapshots for long.
+const DEFAULT_MAX_CATCH_UP: TimeDelta = TimeDelta::hours(48);
+
+/// Fetches every slot on a fixed grid and hands the snapshots to a [`SnapshotStore`].
+///
+/// The store is only used from the blocking thread pool, so that a slow disk or database
+/// doesn't hold up the requests in flight.
+pub struct Scraper<S> {
+    client: AlertClient,
+    store: Arc<Mutex<S>>,
+    variable: Variable,
+    interval: TimeDelta,
+    delay: TimeDelta,
+    max_catch_up: TimeDelta,
+}
+
+impl<S> Scraper<S>
+where
+    S: SnapshotStore + Send + 'static,
+{
+    pub fn new(client: AlertClient, store: S, variable: Variable) -> Self {
+        Self {
+            client,
+            store: Arc::new(Mutex::new(store)),
+            variable,
+            interval: DELTA_15MIN,
+            delay: DEFAULT_DELAY,
+            max_catch_up: DEFAULT_MAX_CATCH_UP,
+        }
+    }
+
+    /// Distance between two scraped slots, a multiple of 15 minutes.
+    pub fn interval(mut self, interval: TimeDelta) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// How long to wait after a slot starts before fetching it.
+    pub fn delay(mut self, delay: TimeDelta) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Oldest slot fetched when resuming, relative to the latest one.
+    pub fn max_catch_up(mut self, max_catch_up: TimeDelta) -> Self {
+        self.max_catch_up = max_catch_up.max(self.interval);
+        self
+    }
+
+    /// Scrapes forever, or until the first pass is done with `once` or the process is
+    /// interrupted, then flushes the store.
+    ///
+    /// Resumes after the last slot in the store; an empty store starts `backfill` before the
+    /// latest slot.
+    pub async fn run(mut self, backfill: TimeDelta, once: bool) -> anyhow::Result<()> {
+        let scraped = tokio::select! {
+            result = self.scrape(backfill, once) => result,
+            result = tokio::signal::ctrl_c() => {
+                info!("Interrupted, flushing the store");
+                result.map_err(Into::into)
+            }
+        };
+        let flushed = self.with_store(|store| store.flush()).await;
+        scraped?;
+        Ok(flushed?)
+    }
+
+    async fn scrape(&mut self, backfill: TimeDelta, once: bool) -> anyhow::Result<()> {
+        let variable = self.variable.clone();
+        let mut next = match self
+            .with_store(move |store| store.last_slot(&variable))
+            .await?
+        {
+            Some(last) => {
+                info!("Resuming after {last}");
+                last + self.interval
+            }
+            None => {
+                let start = self.ready_slot()? - backfill;
+                info!("Empty store, starting from {start}");
+                start
+            }
+        };
+
+        loop {
+            next = self.scrape_until_ready(next).await?;
+            if once {
+                return Ok(());
+            }
+
+            let wait = (next + self.delay - self.client.clock().now())
+                .to_std()
+                .unwrap_or_default();
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Scrapes the slots from `next` up to the latest one published, returning the slot to
+    /// scrape after them.
+    async fn scrape_until_ready(&mut self, next: DateTime<Utc>) -> anyhow::Result<DateTime<Utc>> {
+        let ready = self.ready_slot()?;
+        if next > ready {
+            return Ok(next);
+        }
+
+        let interval = self.interval.num_seconds();
+        let mut start = next;
+        let oldest = ready - self.max_catch_up;
+        if start < oldest {
+            let skipped = ((oldest - start).num_seconds() + interval - 1) / interval;
+            let resumed = start + TimeDelta::seconds(skipped * interval);
+            warn!(
+                "Gap: skipping slots from {start} to {} beyond the catch-up window",
+                resumed - self.interval
+            );
+            start = resumed;
+        }
+        let end = start + TimeDelta::seconds((ready - start).num_seconds() / interval * interval);
+
+        let mut slots =
+            self.client
+                .variable_stations_between(&self.variable, start, end, self.interval)?;
+        let (mut total, mut stored) = (0, 0);
+        while let Some((slot, result)) = slots.next().await {
+            total += 1;
+            match result {
+                Ok(stations) if has_readings(&stations) => {
+                    match self
+                        .with_store(move |store| store.store(slot, &stations))
+                        .await
+                    {
+                        Ok(()) => stored += 1,
+                        Err(error) => warn!("Gap at {slot}: couldn't store it: {error}"),
+                    }
+                }
+                Ok(_) => warn!("Gap at {slot}: no readings"),
+                Err(error) => warn!("Gap at {slot}: {error}"),
+            }
+        }
+        if let Err(error) = self.with_store(|store| store.flush()).await {
+            warn!("Couldn't flush the store, retrying after the next slot: {error}");
+        }
+        info!("Stored {stored} of {total} slots up to {end}");
+
+        Ok(end + self.interval)
+    }
+
+    /// Runs `operation` on the store in the blocking thread pool.
+    async fn with_store<T, F>(&self, operation: F) -> Result<T, StorageError>
+    where
+        F: FnOnce(&mut S) -> Result<T, StorageError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let store = self.store.clone();
+        let task = tokio::task::spawn_blocking(move || {
+            operation(&mut store.lock().unwrap_or_else(PoisonError::into_inner))
+        });
+        match task.await {
+            Ok(result) => result,
+            Err(error) => std::panic::resume_unwind(error.into_panic()),
+        }
+    }
+
+    /// Latest slot upstream is expected to have published.
+    fn ready_slot(&self) -> anyhow::Result<DateTime<Utc>> {
+        Ok(clamp_station_time(self.client.clock().now() - self.delay)?)
+    }
+}
+
+fn has_readings(stations: &Stations) -> bool {
+    stations.iter().any(|station| station.value().is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead as _, BufReader, Write as _},
+        net::TcpListener,
+    };
+
+    use alert_core::api::{FixedClock, RetryPolicy};
+
+    use super::*;
+
+    const SNAPSHOT: &str = r#"[{"idstazione": "-/1,2/simnbo", "ordinamento": 9999,
+        "nomestaz": "Test", "lon": "1", "lat": "2", "value": 1.5,
+        "soglia1": 1, "soglia2": 2, "soglia3": 3}]"#;
+
+    /// Local upstream answering every snapshot request with [`SNAPSHOT`].
+    fn upstream() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut line = String::new();
+                let mut reader = BufReader::new(&stream);
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{SNAPSHOT}",
+                    SNAPSHOT.len()
+                )
+                .unwrap();
+            }
+        });
+        base_url
+    }
+
+    /// Records the stored slots and the flushes, failing to store `broken` and the first
+    /// `failed_flushes` flushes.
+    #[derive(Default)]
+    struct TestStore {
+        stored: Arc<Mutex<Vec<DateTime<Utc>>>>,
+        broken: Option<DateTime<Utc>>,
+        flushes: Arc<Mutex<u32>>,
+        failed_flushes: u32,
+    }
+
+    impl SnapshotStore for TestStore {
+        fn store(&mut self, time: DateTime<Utc>, _: &Stations) -> Result<(), StorageError> {
+            if Some(time) == self.broken {
+                return Err(StorageError::Corrupt("broken slot".to_owned()));
+            }
+            self.stored.lock().unwrap().push(time);
+            Ok(())
+        }
+
+        fn last_slot(&self, _: &Variable) -> Result<Option<DateTime<Utc>>, StorageError> {
+            Ok(self.stored.lock().unwrap().iter().max().copied())
+        }
+
+        fn flush(&mut self) -> Result<(), StorageError> {
+            let mut flushes = self.flushes.lock().unwrap();
+            *flushes += 1;
+            if *flushes <= self.failed_flushes {
+                return Err(StorageError::Corrupt("broken flush".to_owned()));
+            }
+            Ok(())
+        }
+    }
+
+    fn utc(rfc3339: &str) -> DateTime<Utc> {
+        rfc3339.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn store_failures_are_gaps() {
+        let client = AlertClient::builder()
+            .base_url(upstream())
+            .rate_limiter(None)
+            .retry_policy(RetryPolicy::none())
+            .clock(Arc::new(FixedClock::new(utc("2024-05-01T10:20:00Z"))))
+            .build()
+            .unwrap();
+        let store = TestStore {
+            broken: Some(utc("2024-05-01T09:30:00Z")),
+            ..TestStore::default()
+        };
+        let stored = store.stored.clone();
+
+        Scraper::new(client, store, Variable::default())
+            .run(TimeDelta::hours(1), true)
+            .await
+            .unwrap();
+
+        let mut stored = stored.lock().unwrap().clone();
+        stored.sort();
+        assert_eq!(
+            stored,
+            [
+                utc("2024-05-01T09:00:00Z"),
+                utc("2024-05-01T09:15:00Z"),
+                utc("2024-05-01T09:45:00Z"),
+                utc("2024-05-01T10:00:00Z"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn resumes_after_the_last_stored_slot() {
+        let client = AlertClient::builder()
+            .base_url(upstream())
+            .rate_limiter(None)
+            .clock(Arc::new(FixedClock::new(utc("2024-05-01T10:20:00Z"))))
+            .build()
+            .unwrap();
+        let store = TestStore::default();
+        store
+            .stored
+            .lock()
+            .unwrap()
+            .push(utc("2024-05-01T09:30:00Z"));
+        let stored = store.stored.clone();
+
+        Scraper::new(client, store, Variable::default())
+            .run(TimeDelta::hours(24), true)
+            .await
+            .unwrap();
+
+        assert_eq!(stored.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn flushes_again_at_shutdown() {
+        for (failed_flushes, succeeds) in [(1, true), (2, false)] {
+            let client = AlertClient::builder()
+                .base_url(upstream())
+                .rate_limiter(None)
+                .clock(Arc::new(FixedClock::new(utc("2024-05-01T10:20:00Z"))))
+                .build()
+                .unwrap();
+            let store = TestStore {
+                failed_flushes,
+                ..TestStore::default()
+            };
+            let flushes = store.flushes.clone();
+
+            let result = Scraper::new(client, store, Variable::default())
+                .run(TimeDelta::zero(), true)
+                .await;
+
+            assert_eq!(result.is_ok(), succeeds);
+            assert_eq!(*flushes.lock().unwrap(), 2);
+        }
+    }
+}
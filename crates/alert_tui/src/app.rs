@@ -1,14 +1,16 @@
 use crate::{
     framework::{
-        AppMessage, AppModel, AppReaction, MultiPageFrame, PageModel, RenderablePageModel,
-        Task, UiConfig, Update, spawn_input_task,
+        AppMessage, AppModel, AppReaction, MultiPageFrame, PageModel, RenderablePageModel, Task,
+        UiConfig, Update, spawn_input_task,
     },
     pages::{graph, graph::GraphPage, selection, selection::SelectionPage},
 };
+use alert_core::{api::Clock, source::StationSource};
 use async_channel::{Receiver, Sender};
+use chrono_tz::Tz;
 use crossterm::event::Event;
 use ratatui::{Frame, buffer::Buffer, layout::Rect};
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 pub enum AppEvent {
     Selection(selection::Message),
@@ -96,11 +98,21 @@ pub enum PageAction {
 
 pub struct App {
     pages: MultiPageFrame<PageId, Page>,
+    source: Arc<dyn StationSource>,
+    time_zone: Tz,
 }
 
 impl App {
-    pub fn new(pages: MultiPageFrame<PageId, Page>) -> Self {
-        Self { pages }
+    pub fn new(
+        pages: MultiPageFrame<PageId, Page>,
+        source: Arc<dyn StationSource>,
+        time_zone: Tz,
+    ) -> Self {
+        Self {
+            pages,
+            source,
+            time_zone,
+        }
     }
 
     pub fn active_page(&self) -> PageId {
@@ -111,9 +123,20 @@ impl App {
         let _ = self.pages.show(PageId::Selection);
     }
 
-    fn show_graph(&mut self, station: alert_core::model::Station) -> Update<PageAction, Message> {
-        self.pages
-            .insert_and_show(PageId::Graph, Page::Graph(GraphPage::loading(station)));
+    fn show_graph(
+        &mut self,
+        station: alert_core::model::Station,
+        variable: alert_core::api::Variable,
+    ) -> Update<PageAction, Message> {
+        self.pages.insert_and_show(
+            PageId::Graph,
+            Page::Graph(GraphPage::loading(
+                station,
+                variable,
+                self.source.clone(),
+                self.time_zone,
+            )),
+        );
         self.pages.init()
     }
 
@@ -134,8 +157,8 @@ impl App {
                 reaction.should_quit = true;
                 reaction
             }
-            Some(PageAction::Selection(selection::Action::OpenGraph { station })) => {
-                let init = self.show_graph(station);
+            Some(PageAction::Selection(selection::Action::OpenGraph { station, variable })) => {
+                let init = self.show_graph(*station, variable);
                 reaction.redraw = true;
                 reaction.redraw |= init.redraw;
                 reaction.task = Task::batch([reaction.task, init.task]);
@@ -197,7 +220,12 @@ impl AppModel for App {
     }
 }
 
-pub async fn bootstrap(config: UiConfig) -> (App, Sender<Message>, Receiver<Message>) {
+pub async fn bootstrap(
+    config: UiConfig,
+    source: Arc<dyn StationSource>,
+    clock: Arc<dyn Clock>,
+    time_zone: Tz,
+) -> (App, Sender<Message>, Receiver<Message>) {
     let (sender, receiver) = async_channel::bounded::<Message>(256);
 
     spawn_input_task(sender.clone()).await;
@@ -205,10 +233,15 @@ pub async fn bootstrap(config: UiConfig) -> (App, Sender<Message>, Receiver<Mess
     let mut pages = HashMap::new();
     pages.insert(
         PageId::Selection,
-        Page::Selection(SelectionPage::new(config.filter_debounce_interval())),
+        Page::Selection(SelectionPage::new(
+            config.filter_debounce_interval(),
+            source.clone(),
+            clock,
+            time_zone,
+        )),
     );
 
     let frame = MultiPageFrame::new(pages, PageId::Selection);
 
-    (App::new(frame), sender, receiver)
+    (App::new(frame, source, time_zone), sender, receiver)
 }
@@ -1,4 +1,9 @@
-use crate::{app, framework};
+use crate::{app, framework, pages::selection::parse_time_input};
+use alert_core::{
+    api::{AlertClient, Clock, OffsetClock, STATION_TIME_ZONE, SystemClock},
+    source::{FixtureSource, StationSource},
+    storage::SqliteStore,
+};
 use argh::FromArgs;
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
@@ -12,6 +17,8 @@ use ratatui::{
 use std::{
     io,
     panic::{set_hook, take_hook},
+    path::PathBuf,
+    sync::Arc,
 };
 
 #[derive(FromArgs, Debug, Clone)]
@@ -19,6 +26,26 @@ use std::{
 pub struct Args {
     #[argh(option, short = 'f', description = "target fps cap")]
     pub target_fps: Option<u16>,
+    #[argh(
+        option,
+        description = "read stations and time series from a directory of JSON fixtures instead of the live API"
+    )]
+    pub fixtures: Option<PathBuf>,
+    #[argh(
+        option,
+        description = "read stations and time series from a SQLite database written by alert_scraper"
+    )]
+    pub sqlite: Option<PathBuf>,
+    #[argh(
+        option,
+        description = "replay mode: pretend the current time is the given YYYY-MM-DD HH:MM"
+    )]
+    pub now: Option<String>,
+    #[argh(
+        switch,
+        description = "show and read times in UTC instead of Europe/Rome"
+    )]
+    pub utc: bool,
 }
 
 fn init_panic_hook() {
@@ -43,10 +70,29 @@ pub fn restore_tui() -> io::Result<()> {
 
 pub async fn run_tui(args: Args) -> anyhow::Result<()> {
     let config = framework::UiConfig::from_target_fps(args.target_fps);
+    let time_zone = if args.utc {
+        chrono_tz::UTC
+    } else {
+        STATION_TIME_ZONE
+    };
+    let clock: Arc<dyn Clock> = match args.now {
+        Some(now) => {
+            let now =
+                parse_time_input(&now, time_zone).map_err(|err| anyhow::anyhow!("--now: {err}"))?;
+            Arc::new(OffsetClock::starting_at(now.to_utc()))
+        }
+        None => Arc::new(SystemClock),
+    };
+    let source: Arc<dyn StationSource> = match (args.fixtures, args.sqlite) {
+        (Some(dir), None) => Arc::new(FixtureSource::new(dir)),
+        (None, Some(path)) => Arc::new(SqliteStore::open(path)?),
+        (None, None) => Arc::new(AlertClient::builder().clock(clock.clone()).build()?),
+        (Some(_), Some(_)) => anyhow::bail!("--fixtures and --sqlite can't be used together"),
+    };
 
     init_panic_hook();
     let mut terminal = init_tui()?;
-    let (app, sender, receiver) = app::bootstrap(config).await;
+    let (app, sender, receiver) = app::bootstrap(config, source, clock, time_zone).await;
 
     let result = framework::run_app(&mut terminal, app, config, receiver, sender).await;
 
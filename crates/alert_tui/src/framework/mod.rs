@@ -17,7 +17,9 @@ pub enum AppMessage<E> {
     Shutdown,
 }
 
+#[derive(Default)]
 pub enum Task<M> {
+    #[default]
     None,
     Future {
         key: Option<TaskKey>,
@@ -26,12 +28,6 @@ pub enum Task<M> {
     Batch(Vec<Task<M>>),
 }
 
-impl<M> Default for Task<M> {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 impl<M: 'static> Task<M> {
     pub fn none() -> Self {
         Self::None
@@ -1,38 +1,53 @@
 use crate::framework::{PageModel, RenderablePageModel, Task, Update};
 use alert_core::{
-    api::AlertClient,
-    model::{Station, TimeSeries},
+    analytics::{Crossing, PROJECTION_HORIZON, Projection, STATS_WINDOW, Summary, project},
+    api::Variable,
+    model::{AlertLevel, Station, StationId, TimeSeries},
+    source::StationSource,
 };
-use chrono::{Local, TimeZone};
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
 use crossterm::event::{Event, KeyCode, KeyEventKind};
 use ratatui::{
     buffer::Buffer,
     layout::HorizontalAlignment,
-    layout::Rect,
+    layout::{Constraint, Layout, Rect},
     style::{Color, Modifier, Style, Stylize},
     symbols,
-    text::Span,
-    widgets::{Axis, Block, Chart, Dataset, Paragraph, Widget},
+    text::{Line, Span},
+    widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph, Widget},
 };
+use std::sync::Arc;
 
 const MILLIS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
 
 pub enum GraphDataState {
     Loading,
-    Ready { data: Vec<(f64, f64)> },
+    /// Runs of consecutive readings, drawn separately so that outages show up as gaps.
+    Ready {
+        segments: Vec<Vec<(f64, f64)>>,
+        summary: Box<Summary>,
+        projection: Projection,
+    },
     Error(String),
 }
 
+struct ThresholdLine {
+    label: String,
+    color: Color,
+    threshold: f64,
+    data: Vec<(f64, f64)>,
+}
+
 pub struct GraphPage {
     station: Station,
+    variable: Variable,
+    source: Arc<dyn StationSource>,
+    time_zone: Tz,
     data_state: GraphDataState,
-    soglia1_data: Vec<(f64, f64)>,
-    soglia2_data: Vec<(f64, f64)>,
-    soglia3_data: Vec<(f64, f64)>,
-    soglia1_label: String,
-    soglia2_label: String,
-    soglia3_label: String,
+    threshold_lines: Vec<ThresholdLine>,
     window: [f64; 2],
+    y_bounds: [f64; 2],
 }
 
 pub enum Action {
@@ -45,44 +60,59 @@ pub enum Message {
 }
 
 impl GraphPage {
-    pub fn loading(station: Station) -> Self {
-        let soglia1_label = format!("Soglia1 ({})", station.soglia1());
-        let soglia2_label = format!("Soglia2 ({})", station.soglia2());
-        let soglia3_label = format!("Soglia3 ({})", station.soglia3());
+    pub fn loading(
+        station: Station,
+        variable: Variable,
+        source: Arc<dyn StationSource>,
+        time_zone: Tz,
+    ) -> Self {
+        let threshold_lines = station
+            .thresholds()
+            .levels()
+            .map(|(level, threshold)| ThresholdLine {
+                label: format!("{} ({threshold})", level.name()),
+                color: threshold_color(level),
+                threshold: f64::from(threshold),
+                data: Vec::new(),
+            })
+            .collect();
         Self {
-            soglia1_data: Vec::new(),
-            soglia2_data: Vec::new(),
-            soglia3_data: Vec::new(),
             station,
-            soglia1_label,
-            soglia2_label,
-            soglia3_label,
+            variable,
+            source,
+            time_zone,
+            threshold_lines,
             data_state: GraphDataState::Loading,
             window: [0.0, 1.0],
+            y_bounds: [0.0, 1.0],
         }
     }
 
-    pub fn set_series(&mut self, data: TimeSeries) {
-        let data = data.as_dataset();
-        self.soglia1_data = data
-            .iter()
-            .map(|(timestamp, _)| (*timestamp, *self.station.soglia1() as f64))
-            .collect();
-        self.soglia2_data = data
-            .iter()
-            .map(|(timestamp, _)| (*timestamp, *self.station.soglia2() as f64))
-            .collect();
-        self.soglia3_data = data
-            .iter()
-            .map(|(timestamp, _)| (*timestamp, *self.station.soglia3() as f64))
-            .collect();
-
-        self.window = match (data.first(), data.last()) {
-            (Some(first), Some(last)) if first.0 < last.0 => [first.0, last.0],
-            (Some(first), Some(_)) => [first.0, first.0 + 1.0],
+    pub fn set_series(&mut self, series: TimeSeries) {
+        // The window spans missing readings too, so an outage at either end stays visible.
+        let times = series.iter().map(|tv| tv.time().timestamp_millis() as f64);
+        self.window = match (times.clone().reduce(f64::min), times.reduce(f64::max)) {
+            (Some(first), Some(last)) if first < last => [first, last],
+            (Some(first), Some(_)) => [first, first + 1.0],
             _ => [0.0, 1.0],
         };
-        self.data_state = GraphDataState::Ready { data };
+
+        for line in &mut self.threshold_lines {
+            line.data = vec![
+                (self.window[0], line.threshold),
+                (self.window[1], line.threshold),
+            ];
+        }
+        let segments = series.segments();
+        let values = segments.iter().flatten().map(|&(_, value)| value);
+        let thresholds = self.threshold_lines.iter().map(|line| line.threshold);
+        self.y_bounds = value_bounds(values.chain(thresholds));
+
+        self.data_state = GraphDataState::Ready {
+            segments,
+            summary: Box::new(Summary::new(&series, self.station.thresholds())),
+            projection: project(&self.station, &series),
+        };
     }
 
     pub fn set_error(&mut self, error: String) {
@@ -103,37 +133,52 @@ impl GraphPage {
                     .block(Block::bordered().title(self.station.nomestaz().red().bold()))
                     .render(area, buf);
             }
-            GraphDataState::Ready { data } => {
-                let x_labels = build_x_labels(self.window);
-
-                let datasets = vec![
-                    Dataset::default()
-                        .name("Rilevazione")
-                        .marker(symbols::Marker::Dot)
-                        .style(Style::default().fg(Color::Cyan))
-                        .data(data),
-                    Dataset::default()
-                        .name(self.soglia1_label.as_str())
-                        .marker(symbols::Marker::Braille)
-                        .style(Style::default().fg(Color::Green))
-                        .data(&self.soglia1_data),
-                    Dataset::default()
-                        .name(self.soglia2_label.as_str())
-                        .marker(symbols::Marker::Braille)
-                        .style(Style::default().fg(Color::Yellow))
-                        .data(&self.soglia2_data),
-                    Dataset::default()
-                        .name(self.soglia3_label.as_str())
-                        .marker(symbols::Marker::Braille)
-                        .style(Style::default().fg(Color::Red))
-                        .data(&self.soglia3_data),
-                ];
+            GraphDataState::Ready {
+                segments,
+                summary,
+                projection,
+            } => {
+                let [chart_area, summary_area] =
+                    Layout::vertical([Constraint::Min(5), Constraint::Length(5)]).areas(area);
+                let x_labels = build_x_labels(self.window, self.time_zone);
+                let y_labels = build_y_labels(self.y_bounds);
+
+                let datasets = segments
+                    .iter()
+                    .enumerate()
+                    .map(|(index, segment)| {
+                        let dataset = Dataset::default()
+                            .marker(symbols::Marker::Braille)
+                            .graph_type(GraphType::Line)
+                            .style(Style::default().fg(Color::Cyan))
+                            .data(segment);
+                        // A single legend entry for all the segments.
+                        if index == 0 {
+                            dataset.name("Reading")
+                        } else {
+                            dataset
+                        }
+                    })
+                    .chain(self.threshold_lines.iter().map(|line| {
+                        Dataset::default()
+                            .name(line.label.as_str())
+                            .marker(symbols::Marker::Braille)
+                            .graph_type(GraphType::Line)
+                            .style(Style::default().fg(line.color))
+                            .data(&line.data)
+                    }))
+                    .collect::<Vec<_>>();
+
+                let mut title = vec![self.station.nomestaz().cyan().bold()];
+                if self.station.thresholds().is_unknown() {
+                    title.push(" (no thresholds configured)".gray());
+                }
 
                 Chart::new(datasets)
-                    .block(Block::bordered().title(self.station.nomestaz().cyan().bold()))
+                    .block(Block::bordered().title(Line::from(title)))
                     .x_axis(
                         Axis::default()
-                            .title("Time (local)")
+                            .title(format!("Time ({})", self.time_zone.name()))
                             .style(Style::default().fg(Color::Gray))
                             .labels(x_labels)
                             .labels_alignment(HorizontalAlignment::Right)
@@ -141,12 +186,22 @@ impl GraphPage {
                     )
                     .y_axis(
                         Axis::default()
-                            .title("Rilevazione")
+                            .title(format!("Reading ({})", self.variable.unit()))
                             .style(Style::default().fg(Color::Gray))
-                            .labels(vec!["-20".bold(), "0".into(), "20".bold()])
-                            .bounds([-20.0, 40.0]),
+                            .labels(y_labels)
+                            .bounds(self.y_bounds),
                     )
-                    .render(area, buf);
+                    .render(chart_area, buf);
+
+                let mut lines = summary_lines(summary, self.variable.unit(), self.time_zone);
+                lines.push(projection_line(
+                    projection,
+                    self.variable.unit(),
+                    self.time_zone,
+                ));
+                Paragraph::new(lines)
+                    .block(Block::bordered().title("Statistics".cyan().bold()))
+                    .render(summary_area, buf);
             }
         }
     }
@@ -158,7 +213,11 @@ impl PageModel for GraphPage {
 
     fn init(&mut self) -> Update<Self::Action, Self::Message> {
         Update::task(Task::perform(
-            load_timeseries(self.station.idstazione().to_owned()),
+            load_timeseries(
+                self.source.clone(),
+                self.variable.clone(),
+                self.station.idstazione().clone(),
+            ),
             |result| match result {
                 Ok(series) => Message::TimeSeriesLoaded(series),
                 Err(message) => Message::LoadFailed(message),
@@ -167,13 +226,13 @@ impl PageModel for GraphPage {
     }
 
     fn handle_event(&mut self, event: Event) -> Update<Self::Action, Self::Message> {
-        if let Event::Key(key) = event {
-            if key.kind == KeyEventKind::Press {
-                return match key.code {
-                    KeyCode::Esc | KeyCode::Char('q') => Update::action(Action::Back),
-                    _ => Update::none(),
-                };
-            }
+        if let Event::Key(key) = event
+            && key.kind == KeyEventKind::Press
+        {
+            return match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => Update::action(Action::Back),
+                _ => Update::none(),
+            };
         }
 
         Update::none()
@@ -199,21 +258,153 @@ impl RenderablePageModel for GraphPage {
     }
 }
 
-async fn load_timeseries(station_id: String) -> Result<TimeSeries, String> {
-    let client = AlertClient::new();
-    client
-        .station_timeseries(&station_id)
+async fn load_timeseries(
+    source: Arc<dyn StationSource>,
+    variable: Variable,
+    station_id: StationId,
+) -> Result<TimeSeries, String> {
+    source
+        .station_timeseries(&variable, &station_id)
         .await
         .map_err(|error| error.to_string())
 }
 
-fn build_x_labels(window: [f64; 2]) -> Vec<Span<'static>> {
+fn summary_lines(summary: &Summary, unit: &str, time_zone: Tz) -> Vec<Line<'static>> {
+    let format_value = |value: f64| format!("{value:.2} {unit}");
+
+    let mut first = Vec::new();
+    if let Some((_, value)) = summary.latest() {
+        first.push(format!("Latest: {}", format_value(value)));
+    }
+    if let Some(rate) = summary.rate_per_hour() {
+        first.push(format!("Rate (1h): {rate:+.2} {unit}/h"));
+    }
+    if let Some(stats) = summary.stats() {
+        first.push(format!(
+            "{}h min {} | max {} | mean {}",
+            STATS_WINDOW.num_hours(),
+            format_value(stats.min()),
+            format_value(stats.max()),
+            format_value(stats.mean()),
+        ));
+    }
+
+    let mut second = Vec::new();
+    match summary
+        .peaks()
+        .iter()
+        .max_by(|a, b| a.value().total_cmp(&b.value()))
+    {
+        Some(highest) => second.push(format!(
+            "Peaks: {} (highest {} at {})",
+            summary.peaks().len(),
+            format_value(highest.value()),
+            format_timestamp_label(highest.time().timestamp_millis(), time_zone),
+        )),
+        None => second.push("Peaks: none".to_owned()),
+    }
+    for above in summary.time_above() {
+        second.push(format!(
+            "Above {}: {}",
+            above.level().name(),
+            format_duration(above.duration())
+        ));
+    }
+
+    [first, second]
+        .into_iter()
+        .map(|parts| Line::from(parts.join(" | ")))
+        .collect()
+}
+
+fn projection_line(projection: &Projection, unit: &str, time_zone: Tz) -> Line<'static> {
+    let Some(trend) = projection.trend() else {
+        return Line::from("Projection: not enough recent readings");
+    };
+
+    let mut parts = vec![format!("Projection: {:+.2} {unit}/h", trend.per_hour())];
+    parts.extend(projection.crossings().iter().map(|crossing| {
+        format!(
+            "{} {}",
+            crossing.level().name(),
+            format_crossing(crossing.crossing(), time_zone)
+        )
+    }));
+    if projection.crossings().is_empty() {
+        parts.push("no thresholds".to_owned());
+    }
+    Line::from(parts.join(" | "))
+}
+
+/// Short description of a crossing, e.g. `~29 Jun 14:30 (14:05-15:10)`.
+pub(crate) fn format_crossing(crossing: Crossing, time_zone: Tz) -> String {
+    let format = |time: DateTime<Utc>, pattern: &str| {
+        time.with_timezone(&time_zone).format(pattern).to_string()
+    };
+
+    match crossing {
+        Crossing::Exceeded => "exceeded".to_owned(),
+        Crossing::Expected {
+            at,
+            earliest,
+            latest,
+        } => format!(
+            "~{} ({}-{})",
+            format(at, "%d %b %H:%M"),
+            format(earliest, "%H:%M"),
+            latest.map_or_else(|| "?".to_owned(), |latest| format(latest, "%H:%M")),
+        ),
+        Crossing::BeyondHorizon => format!("not within {}h", PROJECTION_HORIZON.num_hours()),
+        Crossing::NotRising => "not rising".to_owned(),
+    }
+}
+
+fn format_duration(duration: TimeDelta) -> String {
+    format!(
+        "{}h{:02}m",
+        duration.num_hours(),
+        duration.num_minutes() % 60
+    )
+}
+
+fn threshold_color(level: AlertLevel) -> Color {
+    match level {
+        AlertLevel::Level3 => Color::Red,
+        AlertLevel::Level2 => Color::Yellow,
+        _ => Color::Green,
+    }
+}
+
+/// Vertical bounds fitting every value, with a margin so that lines don't run along the border.
+fn value_bounds(values: impl IntoIterator<Item = f64>) -> [f64; 2] {
+    let (min, max) = values
+        .into_iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        });
+    if min > max {
+        return [0.0, 1.0];
+    }
+    let margin = if min < max { (max - min) / 20.0 } else { 1.0 };
+    [min - margin, max + margin]
+}
+
+fn build_y_labels(bounds: [f64; 2]) -> Vec<Span<'static>> {
+    let middle = (bounds[0] + bounds[1]) / 2.0;
+    vec![
+        format!("{:.2}", bounds[0]).bold(),
+        format!("{middle:.2}").into(),
+        format!("{:.2}", bounds[1]).bold(),
+    ]
+}
+
+fn build_x_labels(window: [f64; 2], time_zone: Tz) -> Vec<Span<'static>> {
     let start = window[0].round() as i64;
     let end = window[1].round() as i64;
 
     if start >= end {
         return vec![Span::styled(
-            format_timestamp_label(start),
+            format_timestamp_label(start, time_zone),
             Style::default().blue().add_modifier(Modifier::BOLD),
         )];
     }
@@ -229,7 +420,7 @@ fn build_x_labels(window: [f64; 2]) -> Vec<Span<'static>> {
             start + ((span as i128 * index as i128) / segments as i128) as i64
         };
 
-        let label = format_timestamp_label(timestamp);
+        let label = format_timestamp_label(timestamp, time_zone);
         let span = if index == 0 {
             Span::styled(label, Style::default().blue().add_modifier(Modifier::BOLD))
         } else if index == segments {
@@ -247,10 +438,24 @@ fn build_x_labels(window: [f64; 2]) -> Vec<Span<'static>> {
     labels
 }
 
-fn format_timestamp_label(timestamp_ms: i64) -> String {
-    Local
-        .timestamp_millis_opt(timestamp_ms)
-        .single()
-        .map(|time| time.format("%d %b %H:%M").to_string())
+fn format_timestamp_label(timestamp_ms: i64, time_zone: Tz) -> String {
+    DateTime::from_timestamp_millis(timestamp_ms)
+        .map(|time| {
+            time.with_timezone(&time_zone)
+                .format("%d %b %H:%M")
+                .to_string()
+        })
         .unwrap_or_else(|| timestamp_ms.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_fit_readings_and_thresholds() {
+        assert_eq!(value_bounds([0.0, 10.0, 20.0]), [-1.0, 21.0]);
+        assert_eq!(value_bounds([2.0]), [1.0, 3.0]);
+        assert_eq!(value_bounds([]), [0.0, 1.0]);
+    }
+}
@@ -1,11 +1,20 @@
-use crate::framework::{PageModel, RenderablePageModel, Task, Update};
+use crate::{
+    framework::{PageModel, RenderablePageModel, Task, Update},
+    pages::graph::format_crossing,
+};
 use alert_core::{
-    api::{AlertClient, DELTA_15MIN, clamp_station_time, latest_station_time},
-    model::{Station, Stations},
+    analytics::{Projection, project},
+    api::{
+        Clock, DELTA_15MIN, Variable, clamp_station_time, latest_station_time, parse_local_time,
+    },
+    model::{AlertLevel, RejectedStation, Station, StationId, Stations, TimeSeries},
+    source::StationSource,
 };
-use chrono::{DateTime, Local, LocalResult, NaiveDateTime, TimeDelta, TimeZone};
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use frizbee::{Config, match_list};
+use futures_util::StreamExt as _;
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Layout, Margin, Rect},
@@ -16,19 +25,25 @@ use ratatui::{
         ScrollbarOrientation, ScrollbarState, StatefulWidget, Table, TableState,
     },
 };
-use std::time::Duration;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use unicode_width::UnicodeWidthStr;
 
 const FILTER_DEBOUNCE_TASK: &str = "selection/filter_debounce";
 const LOAD_STATIONS_TASK: &str = "selection/load_stations";
+const LOAD_SERIES_TASK: &str = "selection/load_series";
 const QUERY_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
 
-const INFO_TEXT: &str = "(q) quit | (/) filter | (t) set time | (←/→) +/-15m | (n) latest | (↑/↓) move | (Enter) see graph";
+const INFO_TEXT: &str = "(q) quit | (/) filter | (t) set time | (←/→) +/-15m | (n) latest | (v) variable | (↑/↓) move | (Enter) see graph";
 const FILTER_INFO_TEXT: &str =
     "(Esc) exit filter | (Ctrl+C/Ctrl+Bksp) clear | (↑/↓) move | (Enter) see graph";
 const QUERY_INFO_TEXT: &str =
     "(Esc) cancel | (Enter) load time | format YYYY-MM-DD HH:MM | max now";
 const ITEM_HEIGHT: usize = 4;
+/// How far back `load_page_data` walks looking for a populated slot (24 hours).
+const MAX_LOOKBACK_SLOTS: usize = 96;
+/// Stations, from the most severe, whose time series are fetched to project threshold crossings.
+const PROJECTED_STATIONS: usize = 20;
+const PROJECTION_CONCURRENCY: usize = 4;
 
 #[derive(Clone, Copy)]
 enum SelectionPageState {
@@ -39,7 +54,21 @@ enum SelectionPageState {
 
 pub struct LoadedPageData {
     stations: Stations,
-    resolved_time: DateTime<Local>,
+    rejected: Vec<RejectedStation>,
+    resolved_time: DateTime<Tz>,
+    /// Time the lookup was held back by the client rate limiter.
+    waited: Duration,
+}
+
+/// Time series of the selected variable fetched for the projections.
+///
+/// Upstream only serves the most recent readings, so the same series serve every slot on
+/// screen until a new slot is published. Stations whose series couldn't be fetched are kept
+/// as `None`, to not ask again for them at every slot.
+#[derive(Default)]
+struct SeriesCache {
+    latest_slot: Option<DateTime<Utc>>,
+    series: HashMap<StationId, Option<TimeSeries>>,
 }
 
 enum SelectionPageData {
@@ -52,7 +81,7 @@ pub struct SelectionPage {
     state: SelectionPageState,
     data: SelectionPageData,
     items: Vec<Station>,
-    longest_item_lens: (u16, u16, u16, u16, u16),
+    longest_item_lens: (u16, u16, u16, u16, u16, u16),
     scroll_state: ScrollbarState,
     error: Option<String>,
     filter_query: String,
@@ -60,25 +89,44 @@ pub struct SelectionPage {
     time_input_cursor: usize,
     stations_request_inflight: bool,
     filter_debounce_delay: Duration,
+    variable: Variable,
+    source: Arc<dyn StationSource>,
+    clock: Arc<dyn Clock>,
+    time_zone: Tz,
+    projections: HashMap<StationId, Projection>,
+    series_cache: SeriesCache,
 }
 
 pub enum Action {
     Quit,
-    OpenGraph { station: Station },
+    OpenGraph {
+        station: Box<Station>,
+        variable: Variable,
+    },
 }
 
 pub enum Message {
     ApplyFilter,
     StationsLoaded(LoadedPageData),
     LoadFailed(String),
+    SeriesLoaded {
+        variable: Variable,
+        latest_slot: DateTime<Utc>,
+        series: HashMap<StationId, Option<TimeSeries>>,
+    },
 }
 
 impl SelectionPage {
-    pub fn new(filter_debounce_delay: Duration) -> Self {
+    pub fn new(
+        filter_debounce_delay: Duration,
+        source: Arc<dyn StationSource>,
+        clock: Arc<dyn Clock>,
+        time_zone: Tz,
+    ) -> Self {
         Self {
             table_state: TableState::default().with_selected(0),
             state: SelectionPageState::Normal,
-            longest_item_lens: (0, 0, 0, 0, 0),
+            longest_item_lens: (0, 0, 0, 0, 0, 0),
             scroll_state: ScrollbarState::new(0),
             data: SelectionPageData::Loading,
             items: Vec::new(),
@@ -88,6 +136,12 @@ impl SelectionPage {
             time_input_cursor: 0,
             stations_request_inflight: false,
             filter_debounce_delay,
+            variable: Variable::default(),
+            source,
+            clock,
+            time_zone,
+            projections: HashMap::new(),
+            series_cache: SeriesCache::default(),
         }
     }
 
@@ -102,10 +156,28 @@ impl SelectionPage {
         }
     }
 
-    fn loaded_time(&self) -> Option<DateTime<Local>> {
+    fn loaded_time(&self) -> Option<DateTime<Tz>> {
         self.loaded_data().map(|data| data.resolved_time)
     }
 
+    /// Projects the stations on screen from the cached series, as they were at the loaded slot.
+    fn update_projections(&mut self) {
+        self.projections.clear();
+        let SelectionPageData::Loaded(data) = &self.data else {
+            return;
+        };
+
+        let time = data.resolved_time.to_utc();
+        for station in projected_stations(&data.stations) {
+            if let Some(Some(series)) = self.series_cache.series.get(station.idstazione()) {
+                self.projections.insert(
+                    station.idstazione().clone(),
+                    project_at(station, series, time),
+                );
+            }
+        }
+    }
+
     fn enter_filter_mode(&mut self) -> Update<Action, Message> {
         self.state = SelectionPageState::Filter;
         Update::redraw()
@@ -141,6 +213,7 @@ impl SelectionPage {
             KeyCode::Left | KeyCode::Char('h') => self.shift_query_time(-DELTA_15MIN),
             KeyCode::Right | KeyCode::Char('l') => self.shift_query_time(DELTA_15MIN),
             KeyCode::Char('n') => self.jump_to_latest_query_time(),
+            KeyCode::Char('v') => self.cycle_variable(),
             KeyCode::Char('q') | KeyCode::Esc => Update::action(Action::Quit),
             KeyCode::Char('j') | KeyCode::Down => {
                 self.next();
@@ -150,14 +223,27 @@ impl SelectionPage {
                 self.previous();
                 Update::redraw()
             }
-            KeyCode::Enter => self
-                .selected_station()
-                .map(|station| Update::action(Action::OpenGraph { station }))
-                .unwrap_or_else(Update::none),
+            KeyCode::Enter => self.open_graph(),
             _ => Update::none(),
         }
     }
 
+    fn open_graph(&self) -> Update<Action, Message> {
+        let variable = self
+            .loaded_data()
+            .map(|data| data.stations.variable().clone())
+            .unwrap_or_else(|| self.variable.clone());
+
+        self.selected_station()
+            .map(|station| {
+                Update::action(Action::OpenGraph {
+                    station: Box::new(station),
+                    variable,
+                })
+            })
+            .unwrap_or_else(Update::none)
+    }
+
     fn handle_query_mode_key(&mut self, key: KeyEvent) -> Update<Action, Message> {
         if key.modifiers.contains(KeyModifiers::CONTROL) {
             return match key.code {
@@ -168,11 +254,11 @@ impl SelectionPage {
 
         match key.code {
             KeyCode::Esc => self.exit_query_mode(),
-            KeyCode::Enter => match parse_time_input(&self.time_input) {
+            KeyCode::Enter => match parse_time_input(&self.time_input, self.time_zone) {
                 Ok(requested_time) => {
                     self.state = SelectionPageState::Normal;
                     self.set_error(None);
-                    self.load_time(clamp_time_to_latest(requested_time))
+                    self.load_time(clamp_time_to_latest(self.clock.as_ref(), requested_time))
                 }
                 Err(message) => {
                     self.set_error(Some(message));
@@ -320,7 +406,7 @@ impl SelectionPage {
             return Update::none();
         };
 
-        let next_time = clamp_time_to_latest(current_time + delta);
+        let next_time = clamp_time_to_latest(self.clock.as_ref(), current_time + delta);
         if next_time == current_time {
             return Update::none();
         }
@@ -329,29 +415,54 @@ impl SelectionPage {
         self.load_time(next_time)
     }
 
+    fn cycle_variable(&mut self) -> Update<Action, Message> {
+        if self.stations_request_inflight {
+            return Update::none();
+        }
+
+        let known = Variable::KNOWN;
+        let next = known
+            .iter()
+            .position(|variable| *variable == self.variable)
+            .map(|index| (index + 1) % known.len())
+            .unwrap_or(0);
+        self.variable = known[next].clone();
+        self.series_cache = SeriesCache::default();
+
+        let time = self.loaded_time().unwrap_or_else(|| self.latest_time());
+        self.set_error(None);
+        self.load_time(time)
+    }
+
     fn jump_to_latest_query_time(&mut self) -> Update<Action, Message> {
         self.set_error(None);
-        self.load_time(latest_station_time().unwrap())
+        self.load_time(self.latest_time())
     }
 
-    fn load_time(&mut self, requested_time: DateTime<Local>) -> Update<Action, Message> {
+    fn latest_time(&self) -> DateTime<Tz> {
+        latest_station_time(self.clock.as_ref())
+            .unwrap()
+            .with_timezone(&self.time_zone)
+    }
+
+    fn load_time(&mut self, requested_time: DateTime<Tz>) -> Update<Action, Message> {
         if self.stations_request_inflight {
             return Update::none();
         }
         self.request_stations_load(requested_time)
     }
 
-    fn request_stations_load(
-        &mut self,
-        requested_time: DateTime<Local>,
-    ) -> Update<Action, Message> {
+    fn request_stations_load(&mut self, requested_time: DateTime<Tz>) -> Update<Action, Message> {
         if self.stations_request_inflight {
             return Update::none();
         }
 
         self.stations_request_inflight = true;
+        let source = self.source.clone();
+        let clock = self.clock.clone();
+        let variable = self.variable.clone();
         Update::task(Task::keyed(LOAD_STATIONS_TASK, async move {
-            match load_page_data(requested_time).await {
+            match load_page_data(source, clock, variable, requested_time).await {
                 Ok(data) => Message::StationsLoaded(data),
                 Err(message) => Message::LoadFailed(message),
             }
@@ -398,17 +509,18 @@ impl SelectionPage {
     fn apply_filter(&mut self) {
         let selected_id = self
             .selected_station()
-            .map(|station| station.idstazione().to_owned());
+            .map(|station| station.idstazione().clone());
         let filtered_items = self
             .loaded_data()
             .map(|data| filter_stations(&data.stations, &self.filter_query))
             .unwrap_or_default();
 
-        self.set_visible_items(filtered_items, selected_id.as_deref());
+        self.set_visible_items(filtered_items, selected_id.as_ref());
     }
 
-    fn set_visible_items(&mut self, items: Vec<Station>, selected_id: Option<&str>) {
-        self.longest_item_lens = constraint_len_calculator(&items);
+    fn set_visible_items(&mut self, items: Vec<Station>, selected_id: Option<&StationId>) {
+        self.longest_item_lens =
+            constraint_len_calculator(&items, &self.projections, self.time_zone);
         self.scroll_state =
             ScrollbarState::new((items.len().saturating_sub(1)).saturating_mul(ITEM_HEIGHT));
         self.items = items;
@@ -461,18 +573,26 @@ impl SelectionPage {
     }
 
     fn render_table(&mut self, buf: &mut Buffer, area: Rect) {
+        let value_header = format!("Latest reading ({})", self.variable.unit());
         let header = [
-            "Stazione",
-            "Ultima rilevazione",
-            "Soglia1",
-            "Soglia2",
-            "Soglia3",
+            "Station",
+            value_header.as_str(),
+            "Threshold 1",
+            "Threshold 2",
+            "Threshold 3",
+            "Next threshold",
         ]
         .into_iter()
         .map(Cell::from)
         .collect::<Row>()
         .height(1);
-        let rows = self.items.iter().map(station_row);
+        let rows = self.items.iter().map(|station| {
+            station_row(
+                station,
+                self.projections.get(station.idstazione()),
+                self.time_zone,
+            )
+        });
         let bar = " █ ";
         Table::new(
             rows,
@@ -481,7 +601,8 @@ impl SelectionPage {
                 Constraint::Min(self.longest_item_lens.1 + 1),
                 Constraint::Min(self.longest_item_lens.2 + 1),
                 Constraint::Min(self.longest_item_lens.3 + 1),
-                Constraint::Min(self.longest_item_lens.4),
+                Constraint::Min(self.longest_item_lens.4 + 1),
+                Constraint::Min(self.longest_item_lens.5),
             ],
         )
         .header(header)
@@ -520,14 +641,14 @@ impl PageModel for SelectionPage {
     }
 
     fn handle_event(&mut self, event: Event) -> Update<Self::Action, Self::Message> {
-        if let Event::Key(key) = event {
-            if key.kind == KeyEventKind::Press {
-                return match self.state {
-                    SelectionPageState::Normal => self.handle_normal_mode_key(key),
-                    SelectionPageState::Filter => self.handle_filter_mode_key(key),
-                    SelectionPageState::Query => self.handle_query_mode_key(key),
-                };
-            }
+        if let Event::Key(key) = event
+            && key.kind == KeyEventKind::Press
+        {
+            return match self.state {
+                SelectionPageState::Normal => self.handle_normal_mode_key(key),
+                SelectionPageState::Filter => self.handle_filter_mode_key(key),
+                SelectionPageState::Query => self.handle_query_mode_key(key),
+            };
         }
 
         Update::none()
@@ -542,9 +663,50 @@ impl PageModel for SelectionPage {
             Message::StationsLoaded(mut data) => {
                 self.stations_request_inflight = false;
                 data.stations.sort_by_alert_desc();
+                let latest_slot = self.latest_time().to_utc();
+                if self.series_cache.latest_slot != Some(latest_slot) {
+                    self.series_cache = SeriesCache {
+                        latest_slot: Some(latest_slot),
+                        series: HashMap::new(),
+                    };
+                }
+                let missing = projected_stations(&data.stations)
+                    .map(Station::idstazione)
+                    .filter(|id| !self.series_cache.series.contains_key(id))
+                    .cloned()
+                    .collect::<Vec<_>>();
                 self.data = SelectionPageData::Loaded(data);
+                self.update_projections();
                 self.set_error(None);
                 self.apply_filter();
+
+                if missing.is_empty() {
+                    return Update::redraw();
+                }
+                let source = self.source.clone();
+                let variable = self.variable.clone();
+                Update::task(Task::keyed(LOAD_SERIES_TASK, async move {
+                    let series = load_series(source, &variable, missing).await;
+                    Message::SeriesLoaded {
+                        variable,
+                        latest_slot,
+                        series,
+                    }
+                }))
+                .and_redraw()
+            }
+            Message::SeriesLoaded {
+                variable,
+                latest_slot,
+                series,
+            } => {
+                if variable != self.variable || Some(latest_slot) != self.series_cache.latest_slot {
+                    return Update::none();
+                }
+                self.series_cache.series.extend(series);
+                self.update_projections();
+                self.longest_item_lens =
+                    constraint_len_calculator(&self.items, &self.projections, self.time_zone);
                 Update::redraw()
             }
             Message::LoadFailed(message) => {
@@ -573,24 +735,75 @@ fn visible_station_count(stations: &Stations) -> usize {
         .count()
 }
 
-async fn load_page_data(requested_time: DateTime<Local>) -> Result<LoadedPageData, String> {
-    let client = AlertClient::new();
-    let mut resolved_time = clamp_time_to_latest(requested_time);
-
-    loop {
-        match client.stations_at(resolved_time).await {
-            Ok(stations) if has_enough_visible_stations(&stations) => {
+async fn load_page_data(
+    source: Arc<dyn StationSource>,
+    clock: Arc<dyn Clock>,
+    variable: Variable,
+    requested_time: DateTime<Tz>,
+) -> Result<LoadedPageData, String> {
+    let mut resolved_time = clamp_time_to_latest(clock.as_ref(), requested_time);
+    let mut waited = Duration::ZERO;
+
+    for _ in 0..MAX_LOOKBACK_SLOTS {
+        match source
+            .stations_at(&variable, resolved_time.with_timezone(&Utc))
+            .await
+        {
+            Ok(response) if has_enough_visible_stations(response.stations()) => {
+                waited += response.waited();
+                let (stations, rejected) = response.into_parts();
                 return Ok(LoadedPageData {
                     stations,
+                    rejected,
                     resolved_time,
+                    waited,
                 });
             }
-            Ok(_) => {
+            Ok(response) => {
+                waited += response.waited();
                 resolved_time -= DELTA_15MIN;
             }
             Err(error) => return Err(error.to_string()),
         }
     }
+
+    Err(format!(
+        "No readings in the {} hours before {}",
+        MAX_LOOKBACK_SLOTS / 4,
+        format_time(requested_time)
+    ))
+}
+
+async fn load_series(
+    source: Arc<dyn StationSource>,
+    variable: &Variable,
+    ids: Vec<StationId>,
+) -> HashMap<StationId, Option<TimeSeries>> {
+    futures_util::stream::iter(ids)
+        .map(|id| {
+            let source = source.clone();
+            async move {
+                // A station whose series can't be fetched is just left without a projection.
+                let series = source.station_timeseries(variable, &id).await.ok();
+                (id, series)
+            }
+        })
+        .buffer_unordered(PROJECTION_CONCURRENCY)
+        .collect()
+        .await
+}
+
+/// The stations, from the most severe, worth projecting: with a reading and thresholds.
+fn projected_stations(stations: &Stations) -> impl Iterator<Item = &Station> {
+    stations
+        .iter()
+        .filter(|station| station.value().is_some() && !station.thresholds().is_unknown())
+        .take(PROJECTED_STATIONS)
+}
+
+/// Projects `station` as it was at `time`, ignoring the readings of `series` that came later.
+fn project_at(station: &Station, series: &TimeSeries, time: DateTime<Utc>) -> Projection {
+    project(station, &series.between(DateTime::<Utc>::MIN_UTC, time))
 }
 
 fn has_enough_visible_stations(stations: &Stations) -> bool {
@@ -613,36 +826,76 @@ fn filter_stations(stations: &Stations, filter_query: &str) -> Vec<Station> {
         .collect()
 }
 
-fn station_row(station: &Station) -> Row<'_> {
-    let style = match station.value().unwrap_or(&f32::MIN) {
-        x if *x > *station.soglia3() => tailwind::VIOLET.c500,
-        x if *x > *station.soglia2() => tailwind::RED.c500,
-        x if *x > *station.soglia1() => tailwind::YELLOW.c500,
-        _ => tailwind::GREEN.c500,
-    };
+fn station_row<'a>(
+    station: &'a Station,
+    projection: Option<&Projection>,
+    time_zone: Tz,
+) -> Row<'a> {
+    let style = alert_level_color(station.alert_level());
 
     Row::new([
         padded_cell(Line::from(station.nomestaz())),
         padded_cell(Line::from(
             station.value().copied().unwrap_or(0.0).to_string(),
         )),
-        padded_cell(Line::from(station.soglia1().to_string())),
-        padded_cell(Line::from(station.soglia2().to_string())),
-        padded_cell(Line::from(station.soglia3().to_string())),
+        padded_cell(Line::from(format_threshold(station.soglia1()))),
+        padded_cell(Line::from(format_threshold(station.soglia2()))),
+        padded_cell(Line::from(format_threshold(station.soglia3()))),
+        padded_cell(Line::from(format_projection(projection, time_zone))),
     ])
     .height(ITEM_HEIGHT as u16)
     .style(style)
 }
 
+fn alert_level_color(level: AlertLevel) -> Color {
+    match level {
+        AlertLevel::Level3 => tailwind::VIOLET.c500,
+        AlertLevel::Level2 => tailwind::RED.c500,
+        AlertLevel::Level1 => tailwind::YELLOW.c500,
+        AlertLevel::Normal => tailwind::GREEN.c500,
+        AlertLevel::NoThresholds | AlertLevel::NoData => tailwind::SLATE.c400,
+    }
+}
+
+fn format_threshold(threshold: Option<f32>) -> String {
+    threshold
+        .map(|threshold| threshold.to_string())
+        .unwrap_or_else(|| "-".to_owned())
+}
+
+/// The next threshold expected to be crossed, empty for stations that weren't projected.
+fn format_projection(projection: Option<&Projection>, time_zone: Tz) -> String {
+    let Some(projection) = projection else {
+        return String::new();
+    };
+    if projection.trend().is_none() {
+        return "-".to_owned();
+    }
+    if !projection.is_rising() {
+        return "not rising".to_owned();
+    }
+
+    projection
+        .next()
+        .map(|next| {
+            format!(
+                "{} {}",
+                next.level().name(),
+                format_crossing(next.crossing(), time_zone)
+            )
+        })
+        .unwrap_or_else(|| "-".to_owned())
+}
+
 fn padded_cell(content: Line<'_>) -> Cell<'_> {
-    Cell::from(Text::from(vec![
-        Line::default(),
-        Line::from(content),
-        Line::default(),
-    ]))
+    Cell::from(Text::from(vec![Line::default(), content, Line::default()]))
 }
 
-fn constraint_len_calculator(items: &[Station]) -> (u16, u16, u16, u16, u16) {
+fn constraint_len_calculator(
+    items: &[Station],
+    projections: &HashMap<StationId, Projection>,
+    time_zone: Tz,
+) -> (u16, u16, u16, u16, u16, u16) {
     let nomestaz_len = items
         .iter()
         .map(Station::nomestaz)
@@ -659,22 +912,30 @@ fn constraint_len_calculator(items: &[Station]) -> (u16, u16, u16, u16, u16) {
     let soglia1_len = items
         .iter()
         .map(Station::soglia1)
-        .map(f32::to_string)
+        .map(format_threshold)
         .map(|x| UnicodeWidthStr::width(x.as_str()))
         .max()
         .unwrap_or(0);
     let soglia2_len = items
         .iter()
         .map(Station::soglia2)
-        .map(f32::to_string)
+        .map(format_threshold)
         .map(|x| UnicodeWidthStr::width(x.as_str()))
         .max()
         .unwrap_or(0);
     let soglia3_len = items
         .iter()
         .map(Station::soglia3)
-        .map(f32::to_string)
+        .map(format_threshold)
+        .map(|x| UnicodeWidthStr::width(x.as_str()))
+        .max()
+        .unwrap_or(0);
+
+    let projection_len = items
+        .iter()
+        .map(|station| format_projection(projections.get(station.idstazione()), time_zone))
         .map(|x| UnicodeWidthStr::width(x.as_str()))
+        .chain(std::iter::once("Next threshold".width()))
         .max()
         .unwrap_or(0);
 
@@ -684,6 +945,7 @@ fn constraint_len_calculator(items: &[Station]) -> (u16, u16, u16, u16, u16) {
         soglia1_len as u16,
         soglia2_len as u16,
         soglia3_len as u16,
+        projection_len as u16,
     )
 }
 
@@ -700,7 +962,10 @@ fn render_footer(
         format!("Time> {time_input}")
     } else {
         let time = loaded_data
-            .map(|data| format_time(data.resolved_time))
+            .map(|data| {
+                let time = data.resolved_time;
+                format!("{} ({})", format_time(time), time.timezone().name())
+            })
             .unwrap_or_else(|| "loading...".to_owned());
         format!("Time: {time}")
     };
@@ -721,7 +986,23 @@ fn render_footer(
         INFO_TEXT
     };
 
-    let mut status_parts = vec![query_label, filter_label];
+    let variable_label = loaded_data
+        .map(|data| format!("Variable: {}", data.stations.variable()))
+        .unwrap_or_else(|| "Variable: loading...".to_owned());
+
+    let mut status_parts = vec![query_label, variable_label, filter_label];
+    if let Some(rejected) = loaded_data
+        .map(|data| data.rejected.len())
+        .filter(|rejected| *rejected > 0)
+    {
+        status_parts.push(format!("Warning: {rejected} stations in unexpected format"));
+    }
+    if let Some(waited) = loaded_data
+        .map(|data| data.waited)
+        .filter(|waited| !waited.is_zero())
+    {
+        status_parts.push(format!("Rate limited for {:.1}s", waited.as_secs_f64()));
+    }
     if let Some(error) = error {
         status_parts.push(format!("Error: {error}"));
     }
@@ -787,28 +1068,59 @@ fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
 }
 
 #[inline(always)]
-fn clamp_time_to_latest(time: DateTime<Local>) -> DateTime<Local> {
+fn clamp_time_to_latest(clock: &dyn Clock, time: DateTime<Tz>) -> DateTime<Tz> {
     std::cmp::min(
         clamp_station_time(time).unwrap(),
-        latest_station_time().unwrap(),
+        latest_station_time(clock)
+            .unwrap()
+            .with_timezone(&time.timezone()),
     )
 }
 
-fn format_time(time: DateTime<Local>) -> String {
+fn format_time(time: DateTime<Tz>) -> String {
     time.format(QUERY_TIME_FORMAT).to_string()
 }
 
-fn parse_time_input(input: &str) -> Result<DateTime<Local>, String> {
-    let naive = NaiveDateTime::parse_from_str(input.trim(), QUERY_TIME_FORMAT)
-        .map_err(|_| format!("Invalid time. Use format {QUERY_TIME_FORMAT}"))?;
+pub(crate) fn parse_time_input(input: &str, time_zone: Tz) -> Result<DateTime<Tz>, String> {
+    parse_local_time(&time_zone, input, QUERY_TIME_FORMAT)
+        .map_err(|_| format!("Invalid time. Use format {QUERY_TIME_FORMAT}"))
+}
 
-    match Local.from_local_datetime(&naive) {
-        LocalResult::Single(time) => Ok(time),
-        LocalResult::Ambiguous(_, _) => {
-            Err("Ambiguous local time. Please choose a different minute.".to_owned())
-        }
-        LocalResult::None => {
-            Err("Invalid local time. Please choose a different minute.".to_owned())
-        }
+#[cfg(test)]
+mod tests {
+    use alert_core::model::{StationInfo, Thresholds, TimeValue};
+
+    use super::*;
+
+    #[test]
+    fn projections_ignore_readings_after_the_slot() {
+        let info = StationInfo::new(
+            "-/1,2/simnbo".parse().unwrap(),
+            9999,
+            "Test",
+            "1",
+            "2",
+            Thresholds::new(Some(5.0), None, None),
+        );
+        let station = Station::from_parts(info, Some(1.0));
+        // Rising for two hours, then falling for two more.
+        let series = TimeSeries::new(
+            (0..=16)
+                .map(|step| {
+                    let value = 1.0 + 0.25 * f64::from(8 - (step - 8_i32).abs());
+                    let time = DateTime::UNIX_EPOCH + DELTA_15MIN * step;
+                    TimeValue::new(time, Some(value)).unwrap()
+                })
+                .collect(),
+        );
+        let peak = DateTime::UNIX_EPOCH + TimeDelta::hours(2);
+
+        assert!(project_at(&station, &series, peak).is_rising());
+        assert!(!project_at(&station, &series, peak + TimeDelta::hours(2)).is_rising());
+        assert!(
+            project_at(&station, &series, DateTime::UNIX_EPOCH)
+                .trend()
+                .is_none()
+        );
     }
 }